@@ -66,7 +66,8 @@ fn main() -> anyhow::Result<ExitCode> {
 
 #[mutants::skip] // TODO: cli tests
 fn print_results(difference: &Difference) {
-    eprintln!("{:#?}", difference.histogram());
+    let histogram = difference.histogram();
+    eprintln!("{histogram:#?}\n{histogram}");
 }
 
 #[mutants::skip] // TODO: cli tests
@@ -0,0 +1,438 @@
+use core::fmt;
+use std::collections::BTreeMap;
+
+use imgref::ImgRef;
+
+use crate::diff::{dimensions, interior};
+use crate::displacement::find_match;
+use crate::{Difference, Direction, PixelDifference, RgbaPixel};
+
+/// A probable cause of a difference between images, as determined by
+/// [`Difference::classify()`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum DifferenceKind {
+    /// The pixels would match if the neighborhood compared were larger;
+    /// that is, the content is present in both images but moved by more than 1 pixel.
+    Displacement {
+        /// The distance, in pixels horizontally or vertically (whichever is larger),
+        /// to the nearest match.
+        distance: usize,
+    },
+    /// The pixels are in the right place, but their colors differ by an offset which is
+    /// shared by most of the pixels of the same `expected` color.
+    ColorShift {
+        /// The amount added to each channel of the `expected` pixels to produce the
+        /// `actual` pixels.
+        offset: [i16; 4],
+    },
+    /// The pixels do not match anything nearby; that is, something was drawn in one
+    /// image and not the other.
+    Feature,
+}
+
+/// A summary of the probable causes of the differences in a [`Difference`],
+/// produced by [`Difference::classify()`].
+///
+/// Its [`Display`](fmt::Display) implementation produces a brief, human-readable summary
+/// such as “90% displacement by 2 px, 10% feature added or removed”.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Classification {
+    /// Sorted by decreasing count.
+    counts: Vec<(DifferenceKind, usize)>,
+}
+
+impl Classification {
+    /// The largest displacement, in pixels, which [`Difference::classify()`] searches for.
+    pub const MAX_DISPLACEMENT: usize = 4;
+
+    /// Returns each kind of difference found and the number of pixels classified as that kind,
+    /// sorted from most to least common.
+    #[must_use]
+    pub fn counts(&self) -> &[(DifferenceKind, usize)] {
+        &self.counts
+    }
+
+    /// Returns the most common kind of difference, or [`None`] if there were no differences.
+    #[must_use]
+    pub fn dominant(&self) -> Option<DifferenceKind> {
+        self.counts.first().map(|&(kind, _)| kind)
+    }
+
+    /// Returns the number of pixels which were classified.
+    #[must_use]
+    pub fn pixel_count(&self) -> usize {
+        self.counts.iter().map(|&(_, count)| count).sum()
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.pixel_count();
+        if total == 0 {
+            return write!(f, "no differences");
+        }
+        for (i, &(kind, count)) in self.counts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}% ", (count * 100 + total / 2) / total)?;
+            match kind {
+                DifferenceKind::Displacement { distance } => {
+                    write!(f, "displacement by {distance} px")?;
+                }
+                DifferenceKind::ColorShift {
+                    offset: [red, green, blue, alpha],
+                } => write!(
+                    f,
+                    "color shift by ({red:+}, {green:+}, {blue:+}, {alpha:+})"
+                )?,
+                DifferenceKind::Feature => write!(f, "feature added or removed")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Difference {
+    /// Classifies the differing pixels by their probable cause, to help diagnose why a
+    /// comparison failed without inspecting the diff image.
+    ///
+    /// Each pixel whose difference magnitude is greater than `level` is classified as
+    /// the first of these which applies:
+    ///
+    /// * [`DifferenceKind::Displacement`] if it would match within a neighborhood of up to
+    ///   [`Classification::MAX_DISPLACEMENT`] pixels,
+    /// * [`DifferenceKind::ColorShift`] if the difference between the `actual` and `expected`
+    ///   colors is exactly the same as at at least half of the compared pixels which have
+    ///   the same `expected` color,
+    /// * [`DifferenceKind::Feature`] otherwise.
+    ///
+    /// Thus, a change which alters every occurrence of a color, such as a material being
+    /// drawn in the wrong color, is a color shift, even if it changes different colors by
+    /// different amounts (as a change of gamma does), in which case there is a separate
+    /// [`DifferenceKind::ColorShift`] for each offset.
+    /// However, the offsets must match exactly, so a change which varies between pixels of
+    /// the same color, such as added noise or dithering, is classified as
+    /// [`DifferenceKind::Feature`].
+    ///
+    /// `actual` and `expected` must be the same images that were given to [`diff()`].
+    /// If the images had different sizes, there are no pixels to classify.
+    /// If [color correction](crate::Differ::correct_color) or
+    /// [edge comparison](crate::Differ::compare_edges) was used, the pixels are classified
+    /// according to the corrected image or the edge maps, which were what was compared.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `actual` or `expected` is not of the same size as the originals.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, DifferenceKind};
+    ///
+    /// let mut expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 20 * 20], 20, 20);
+    /// let mut actual = expected.clone();
+    /// // A vertical line which is 3 pixels to the right of where it should be.
+    /// for y in 0..20usize {
+    ///     expected[(8usize, y)] = [255, 255, 255, 255];
+    ///     actual[(11usize, y)] = [255, 255, 255, 255];
+    /// }
+    ///
+    /// let classification = rendiff::diff(actual.as_ref(), expected.as_ref())
+    ///     .classify(actual.as_ref(), expected.as_ref(), 0);
+    ///
+    /// assert_eq!(
+    ///     classification.dominant(),
+    ///     Some(DifferenceKind::Displacement { distance: 3 })
+    /// );
+    /// assert_eq!(classification.to_string(), "100% displacement by 3 px");
+    /// ```
+    ///
+    /// [`diff()`]: crate::diff()
+    #[must_use]
+    pub fn classify(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        level: u8,
+    ) -> Classification {
+        if !self.sizes_matched() {
+            return Classification { counts: Vec::new() };
+        }
+        assert_eq!(
+            dimensions(actual),
+            dimensions(expected),
+            "images given to classify() must have the same size"
+        );
+        self.with_compared_images(actual, expected, |[actual, expected]| {
+            self.classify_compared(actual, expected, level)
+        })
+    }
+
+    /// Implementation of [`Difference::classify()`], given the images which were compared
+    /// pixel-by-pixel.
+    fn classify_compared(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        level: u8,
+    ) -> Classification {
+        let [origin_x, origin_y] = self.compared_origin();
+
+        let mut counts: BTreeMap<DifferenceKind, usize> = BTreeMap::new();
+        // Number of pixels which were not displaced, grouped by color offset and
+        // expected color.
+        let mut by_offset: BTreeMap<([i16; 4], RgbaPixel), usize> = BTreeMap::new();
+
+        for d in self.differing_pixels().filter(|d| d.magnitude > level) {
+            // Convert to the coordinates of the compared parts.
+            let mut d = d;
+            d.x -= origin_x;
+            d.y -= origin_y;
+            if let Some(distance) = displacement(actual, expected, &d) {
+                *counts
+                    .entry(DifferenceKind::Displacement { distance })
+                    .or_default() += 1;
+            } else {
+                let expected_pixel = expected[(d.x, d.y)];
+                let offset = color_offset(actual[(d.x, d.y)], expected_pixel);
+                *by_offset.entry((offset, expected_pixel)).or_default() += 1;
+            }
+        }
+
+        // Count all of the compared pixels of each expected color which had an offset.
+        let mut color_totals: BTreeMap<RgbaPixel, usize> =
+            by_offset.keys().map(|&(_, color)| (color, 0)).collect();
+        for pixel in interior(expected).sub_image(expected).pixels() {
+            if let Some(total) = color_totals.get_mut(&pixel) {
+                *total += 1;
+            }
+        }
+
+        for ((offset, color), count) in by_offset {
+            let kind = if count * 2 >= color_totals[&color] {
+                DifferenceKind::ColorShift { offset }
+            } else {
+                DifferenceKind::Feature
+            };
+            *counts.entry(kind).or_default() += count;
+        }
+
+        let mut counts: Vec<(DifferenceKind, usize)> = counts.into_iter().collect();
+        // Stable sort preserves the kind ordering among equal counts.
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        Classification { counts }
+    }
+}
+
+/// Returns the smallest neighborhood radius, no greater than
+/// [`Classification::MAX_DISPLACEMENT`], within
+/// which each differing direction of the pixel `d` finds a match,
+/// or [`None`] if there is no such radius.
+fn displacement(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    d: &PixelDifference,
+) -> Option<usize> {
+    let mut distance = 0;
+    for (direction, have, want) in [
+        (Direction::Missing, expected, actual),
+        (Direction::Extra, actual, expected),
+    ] {
+        if d.directional_magnitude(direction) > 0 {
+            distance = distance.max(match_distance(have[(d.x, d.y)], want, d.x, d.y)?);
+        }
+    }
+    (distance > 0).then_some(distance)
+}
+
+/// Returns the smallest radius, no greater than [`Classification::MAX_DISPLACEMENT`], of a square
+/// neighborhood of `(x, y)` in `image` containing a pixel which matches `pixel`.
+fn match_distance(
+    pixel: RgbaPixel,
+    image: ImgRef<'_, RgbaPixel>,
+    x: usize,
+    y: usize,
+) -> Option<usize> {
+    let [dx, dy] = find_match(pixel, image, x, y, Classification::MAX_DISPLACEMENT, 0)?;
+    Some(usize::from(dx.unsigned_abs().max(dy.unsigned_abs())))
+}
+
+fn color_offset(actual: RgbaPixel, expected: RgbaPixel) -> [i16; 4] {
+    core::array::from_fn(|i| i16::from(actual[i]) - i16::from(expected[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+    use crate::Differ;
+
+    fn classify(actual: ImgRef<'_, RgbaPixel>, expected: ImgRef<'_, RgbaPixel>) -> Classification {
+        crate::diff(actual, expected).classify(actual, expected, 0)
+    }
+
+    #[test]
+    fn identical() {
+        let image =
+            crate::image::from_fn(10, 10, |x, y| luma_to_rgba(u8::try_from(x * y).unwrap()));
+        let classification = classify(image.as_ref(), image.as_ref());
+        assert_eq!(classification.counts(), &[]);
+        assert_eq!(classification.dominant(), None);
+        assert_eq!(classification.to_string(), "no differences");
+    }
+
+    #[test]
+    fn color_shift() {
+        let expected =
+            crate::image::from_fn(10, 10, |x, _| luma_to_rgba(u8::try_from(x * 10).unwrap()));
+        let actual = crate::image::from_fn(10, 10, |x, y| {
+            let [r, g, b, a] = expected[(x, y)];
+            [r + 30, g, b, a]
+        });
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert_eq!(
+            classification.counts(),
+            &[(
+                DifferenceKind::ColorShift {
+                    offset: [30, 0, 0, 0]
+                },
+                64
+            )]
+        );
+        assert_eq!(
+            classification.to_string(),
+            "100% color shift by (+30, +0, +0, +0)"
+        );
+    }
+
+    /// A uniform change to a region of a single color is a color shift, not a feature.
+    #[test]
+    fn color_shift_of_flat_region() {
+        let inside = |x: usize, y: usize| (2..8).contains(&x) && (3..7).contains(&y);
+        let expected =
+            crate::image::from_fn(10, 10, |x, y| luma_to_rgba(u8::from(inside(x, y)) * 100));
+        let actual =
+            crate::image::from_fn(10, 10, |x, y| luma_to_rgba(u8::from(inside(x, y)) * 110));
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert_eq!(
+            classification.counts(),
+            &[(
+                DifferenceKind::ColorShift {
+                    offset: [10, 10, 10, 0]
+                },
+                24
+            )]
+        );
+    }
+
+    /// A few pixels of another color with the same offset do not prevent the uniform change
+    /// from being a color shift.
+    #[test]
+    fn color_shift_of_flat_region_with_speck() {
+        let inside = |x: usize, y: usize| (2..8).contains(&x) && (3..7).contains(&y);
+        let expected =
+            crate::image::from_fn(10, 10, |x, y| luma_to_rgba(u8::from(inside(x, y)) * 100));
+        let actual = crate::image::from_fn(10, 10, |x, y| {
+            luma_to_rgba(u8::from(inside(x, y)) * 110 + u8::from((x, y) == (8, 8)) * 10)
+        });
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert_eq!(
+            classification.counts(),
+            &[
+                (
+                    DifferenceKind::ColorShift {
+                        offset: [10, 10, 10, 0]
+                    },
+                    24
+                ),
+                (DifferenceKind::Feature, 1),
+            ]
+        );
+    }
+
+    /// Different offsets for different colors, as from a change of gamma, are color shifts.
+    #[test]
+    fn color_shift_varying_by_color() {
+        let expected =
+            crate::image::from_fn(10, 10, |x, _| luma_to_rgba(u8::try_from(x * 20).unwrap()));
+        let actual = crate::image::from_fn(10, 10, |x, _| {
+            luma_to_rgba(u8::try_from(x * 20 + x * x / 2).unwrap())
+        });
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert!(
+            classification
+                .counts()
+                .iter()
+                .all(|(kind, _)| matches!(kind, DifferenceKind::ColorShift { .. })),
+            "{classification}"
+        );
+    }
+
+    /// When edges or color-corrected images were compared, those are what is classified.
+    #[test]
+    fn compared_images_with_options() {
+        // A dot which is both moved and brightened.
+        let image = |dot_x: usize, brightness: u8| {
+            crate::image::from_fn(20, 20, move |x, y| {
+                let value = if (x, y) == (dot_x, 8) {
+                    200
+                } else {
+                    u8::try_from(y * 5).unwrap()
+                };
+                luma_to_rgba(value + brightness)
+            })
+        };
+        let expected = image(8, 0);
+        let actual = image(10, 20);
+        for mut differ in [
+            Differ::new().compare_edges(true),
+            Differ::new().correct_color(true),
+        ] {
+            let classification = differ.compare(actual.as_ref(), expected.as_ref()).classify(
+                actual.as_ref(),
+                expected.as_ref(),
+                0,
+            );
+            assert_eq!(
+                classification.to_string(),
+                "100% displacement by 2 px",
+                "{differ:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn feature() {
+        let expected = crate::image::from_fn(10, 10, |_, _| luma_to_rgba(0));
+        let actual = crate::image::from_fn(10, 10, |x, y| {
+            luma_to_rgba(u8::from((4..6).contains(&x) && (4..6).contains(&y)) * 200)
+        });
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert_eq!(classification.counts(), &[(DifferenceKind::Feature, 4)]);
+        assert_eq!(classification.to_string(), "100% feature added or removed");
+    }
+
+    #[test]
+    fn mixed() {
+        // A line displaced by 2 pixels, and an unrelated dot.
+        let expected = crate::image::from_fn(12, 12, |x, _| luma_to_rgba(u8::from(x == 3) * 255));
+        let actual = crate::image::from_fn(12, 12, |x, y| {
+            luma_to_rgba(u8::from(x == 5) * 255 + u8::from((x, y) == (9, 6)) * 100)
+        });
+        let classification = classify(actual.as_ref(), expected.as_ref());
+        assert_eq!(
+            classification.counts(),
+            &[
+                (DifferenceKind::Displacement { distance: 2 }, 20),
+                (DifferenceKind::Feature, 1),
+            ]
+        );
+        assert_eq!(
+            classification.to_string(),
+            "95% displacement by 2 px, 5% feature added or removed"
+        );
+    }
+}
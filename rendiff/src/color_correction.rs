@@ -0,0 +1,168 @@
+use core::hash::{Hash, Hasher};
+
+use imgref::{ImgRef, ImgVec};
+
+use crate::RgbaPixel;
+
+#[cfg(doc)]
+use crate::{Differ, Difference};
+
+/// A per-channel linear relationship between the colors of two images, as fitted by
+/// [`Differ::correct_color()`].
+///
+/// Each channel value of the `actual` image is approximately
+/// `gain * expected_value + offset` for the corresponding `expected` value, with values on
+/// the 0–255 scale of the images.
+/// The channels are in RGBA order, and are the encoded values, not linear light.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct ColorCorrection {
+    /// Factor by which each channel was scaled.
+    pub gain: [f32; 4],
+    /// Amount added to each channel after scaling.
+    pub offset: [f32; 4],
+}
+
+impl ColorCorrection {
+    /// The correction which makes no change.
+    pub const IDENTITY: Self = Self {
+        gain: [1.0; 4],
+        offset: [0.0; 4],
+    };
+
+    /// Fits a correction such that applying it to `expected` approximates `actual`.
+    ///
+    /// Pixels which fit much worse than the rest are excluded from the final fit, so that
+    /// localized differences do not distort it.
+    /// The images must have the same size.
+    pub(crate) fn fit(actual: ImgRef<'_, RgbaPixel>, expected: ImgRef<'_, RgbaPixel>) -> Self {
+        let mut correction = Self::IDENTITY;
+        for channel in 0..4 {
+            let values = || {
+                actual
+                    .pixels()
+                    .zip(expected.pixels())
+                    .map(move |(a, e)| (f64::from(e[channel]), f64::from(a[channel])))
+            };
+            let (gain, offset) = fit_line(values());
+
+            // Refit without the outliers.
+            let residual = |(e, a): (f64, f64)| a - (gain * e + offset);
+            let (sum_squares, count) = values()
+                .map(residual)
+                .fold((0.0, 0.0), |(s, n), r| (s + r * r, n + 1.0));
+            let tolerance = (3.0 * (sum_squares / count).sqrt()).max(1.0);
+            let (gain, offset) = fit_line(values().filter(|&p| residual(p).abs() <= tolerance));
+
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                correction.gain[channel] = gain as f32;
+                correction.offset[channel] = offset as f32;
+            }
+        }
+        correction
+    }
+
+    /// Returns whether this correction makes no change.
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Transforms `actual` by the inverse of this correction, so that it can be compared with
+    /// the `expected` image, writing the result into `buffer`.
+    pub(crate) fn undo(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        mut buffer: Vec<RgbaPixel>,
+    ) -> ImgVec<RgbaPixel> {
+        buffer.clear();
+        buffer.extend(actual.pixels().map(|pixel| {
+            core::array::from_fn(|channel| {
+                let gain = self.gain[channel];
+                if gain.abs() < 1e-6 {
+                    return pixel[channel];
+                }
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let corrected = ((f32::from(pixel[channel]) - self.offset[channel]) / gain)
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+                corrected
+            })
+        }));
+        ImgVec::new(buffer, actual.width(), actual.height())
+    }
+
+    fn bits(&self) -> ([u32; 4], [u32; 4]) {
+        (self.gain.map(f32::to_bits), self.offset.map(f32::to_bits))
+    }
+}
+
+/// Compares the parameters exactly.
+impl PartialEq for ColorCorrection {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+impl Eq for ColorCorrection {}
+impl Hash for ColorCorrection {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+/// Least-squares fit of `y = gain * x + offset` to the given `(x, y)` points.
+/// If `x` does not vary, the gain is 1.
+fn fit_line(points: impl Iterator<Item = (f64, f64)>) -> (f64, f64) {
+    let [mut n, mut sum_x, mut sum_y, mut sum_x_squared, mut sum_products] = [0.0; 5];
+    for (x, y) in points {
+        n += 1.0;
+        sum_x += x;
+        sum_y += y;
+        sum_x_squared += x * x;
+        sum_products += x * y;
+    }
+    if n == 0.0 {
+        return (1.0, 0.0);
+    }
+    let denominator = n * sum_x_squared - sum_x * sum_x;
+    let gain = if denominator.abs() < 1e-9 {
+        1.0
+    } else {
+        (n * sum_products - sum_x * sum_y) / denominator
+    };
+    (gain, (sum_y - gain * sum_x) / n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_ignores_outliers() {
+        let expected = crate::image::from_fn(20, 20, |x, y| {
+            let value = u8::try_from(x * 10 + y).unwrap();
+            [value, value, value, 255]
+        });
+        let actual = crate::image::from_fn(20, 20, |x, y| {
+            let [r, g, b, a] = expected[(x, y)];
+            if (x, y) == (5, 5) {
+                [255, 0, 255, 255]
+            } else {
+                [r / 2 + 10, g, b.saturating_add(7), a]
+            }
+        });
+        let correction = ColorCorrection::fit(actual.as_ref(), expected.as_ref());
+        assert!((correction.gain[0] - 0.5).abs() < 0.01, "{correction:?}");
+        assert!((correction.offset[0] - 10.0).abs() < 1.0, "{correction:?}");
+        assert!((correction.gain[1] - 1.0).abs() < 0.001, "{correction:?}");
+        assert!((correction.offset[2] - 7.0).abs() < 0.01, "{correction:?}");
+        assert_eq!(
+            (correction.gain[3], correction.offset[3]),
+            (
+                ColorCorrection::IDENTITY.gain[3],
+                ColorCorrection::IDENTITY.offset[3]
+            )
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use imgref::ImgRef;
+
+use crate::diff::pixel_diff;
+use crate::worst::Hex;
+use crate::RgbaPixel;
+
+/// Output of [`color_differences()`]; the colors which appear in only one of two images.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ColorDifferences {
+    missing: Vec<ColorCount>,
+    extra: Vec<ColorCount>,
+}
+
+/// A color and the number of pixels of an image which have that color; part of
+/// [`ColorDifferences`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct ColorCount {
+    /// The color, as it appears in the image.
+    pub color: RgbaPixel,
+    /// The number of pixels of that color.
+    pub count: usize,
+}
+
+/// Compares the sets of colors used in two images, regardless of where they appear, and
+/// reports colors which appear in one image but not the other.
+///
+/// A color is considered to appear in both images if the other image has any pixel whose
+/// difference from it is no greater than `tolerance`, on the same scale as the
+/// [`Histogram`](crate::Histogram) produced by [`diff()`](crate::diff()).
+///
+/// This is a check independent of [`diff()`](crate::diff()), which catches, for example,
+/// a surface which is drawn in the wrong color everywhere it appears, even when its shape
+/// also differs and so the pixel-by-pixel comparison is noisy.
+/// The images need not be the same size.
+///
+/// The comparison takes time proportional to the number of pixels, plus, if `tolerance` is
+/// not zero, the number of distinct colors of each image which are not exactly present in
+/// the other, times the number of distinct colors in that other image.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::imgref::ImgVec;
+///
+/// let mut expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+/// let mut actual = expected.clone();
+/// for x in 2..5usize {
+///     expected[(x, 3usize)] = [255, 0, 0, 255];
+///     actual[(x + 4, 6usize)] = [0, 0, 255, 255];
+/// }
+///
+/// let differences = rendiff::color_differences(actual.as_ref(), expected.as_ref(), 0);
+/// assert_eq!(
+///     differences.to_string(),
+///     "missing #FF0000FF ×3\nextra #0000FFFF ×3\n"
+/// );
+/// ```
+#[must_use]
+pub fn color_differences(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    tolerance: u8,
+) -> ColorDifferences {
+    let actual_counts = count_colors(actual);
+    let expected_counts = count_colors(expected);
+    ColorDifferences {
+        missing: unmatched_colors(&expected_counts, &actual_counts, tolerance),
+        extra: unmatched_colors(&actual_counts, &expected_counts, tolerance),
+    }
+}
+
+impl ColorDifferences {
+    /// Returns the colors which appear in the `expected` image but not the `actual` image,
+    /// with the number of pixels of `expected` which have each color.
+    ///
+    /// They are sorted from most to fewest pixels, and then by color.
+    #[must_use]
+    pub fn missing(&self) -> &[ColorCount] {
+        &self.missing
+    }
+
+    /// Returns the colors which appear in the `actual` image but not the `expected` image,
+    /// with the number of pixels of `actual` which have each color.
+    ///
+    /// They are sorted from most to fewest pixels, and then by color.
+    #[must_use]
+    pub fn extra(&self) -> &[ColorCount] {
+        &self.extra
+    }
+
+    /// Returns whether every color of each image appears in the other.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Prints one line for each missing or extra color, such as `missing #FF0000FF ×120`.
+impl fmt::Display for ColorDifferences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, colors) in [("missing", &self.missing), ("extra", &self.extra)] {
+            for &ColorCount { color, count } in colors {
+                writeln!(f, "{label} {} ×{count}", Hex(color))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn count_colors(image: ImgRef<'_, RgbaPixel>) -> HashMap<RgbaPixel, usize> {
+    let mut counts = HashMap::new();
+    for pixel in image.pixels() {
+        *counts.entry(pixel).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Returns the colors in `have` which have no match in `other`, sorted.
+fn unmatched_colors(
+    have: &HashMap<RgbaPixel, usize>,
+    other: &HashMap<RgbaPixel, usize>,
+    tolerance: u8,
+) -> Vec<ColorCount> {
+    let mut unmatched: Vec<ColorCount> = have
+        .iter()
+        .filter(|&(color, _)| {
+            !other.contains_key(color)
+                && (tolerance == 0
+                    || other
+                        .keys()
+                        .all(|&other_color| pixel_diff(*color, other_color) > tolerance))
+        })
+        .map(|(&color, &count)| ColorCount { color, count })
+        .collect();
+    unmatched.sort_by_key(|c| (core::cmp::Reverse(c.count), c.color));
+    unmatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn tolerance_and_order() {
+        let expected =
+            crate::image::from_fn(6, 4, |x, _| luma_to_rgba([10, 20, 30, 40, 40, 40][x]));
+        let actual = crate::image::from_fn(4, 4, |x, y| {
+            luma_to_rgba(if y == 0 { 100 } else { [10, 23, 50, 50][x] })
+        });
+
+        let exact = color_differences(actual.as_ref(), expected.as_ref(), 0);
+        assert_eq!(
+            exact.missing(),
+            [
+                ColorCount {
+                    color: luma_to_rgba(40),
+                    count: 12
+                },
+                ColorCount {
+                    color: luma_to_rgba(20),
+                    count: 4
+                },
+                ColorCount {
+                    color: luma_to_rgba(30),
+                    count: 4
+                },
+            ]
+        );
+        assert_eq!(
+            exact.extra(),
+            [
+                ColorCount {
+                    color: luma_to_rgba(50),
+                    count: 6
+                },
+                ColorCount {
+                    color: luma_to_rgba(100),
+                    count: 4
+                },
+                ColorCount {
+                    color: luma_to_rgba(23),
+                    count: 3
+                },
+            ]
+        );
+
+        let tolerant = color_differences(actual.as_ref(), expected.as_ref(), 10);
+        assert_eq!(
+            tolerant.to_string(),
+            "extra #646464FF ×4\n",
+            "{tolerant:#?}"
+        );
+        assert!(!tolerant.is_empty());
+        assert!(color_differences(actual.as_ref(), actual.as_ref(), 0).is_empty());
+    }
+}
@@ -1,6 +1,10 @@
+use core::fmt;
+use core::mem;
+
 use imgref::{ImgRef, ImgVec};
 
-use crate::{Histogram, RgbaPixel};
+use crate::translation::{overlap_origin, overlapping_parts};
+use crate::{ColorCorrection, Histogram, QualityMetrics, Rect, RgbaPixel};
 
 /// Output of [`diff()`]; a comparison between two images.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -8,7 +12,104 @@ use crate::{Histogram, RgbaPixel};
 pub struct Difference {
     histogram: Histogram,
 
-    diff_image: Option<imgref::ImgVec<RgbaPixel>>,
+    /// Histograms of each half of the comparison, indexed by [`Direction::index()`].
+    directional_histograms: [Histogram; 2],
+
+    magnitudes: Magnitudes,
+
+    diff_image: Option<ImgVec<RgbaPixel>>,
+
+    /// The translation found, if [`Differ::search_translation()`] was used.
+    translation: Option<[isize; 2]>,
+
+    /// The translation which was undone before comparing, if
+    /// [`Differ::compensate_translation()`] was used, and otherwise zero.
+    /// The magnitudes are then of the overlapping parts of the images given by
+    /// [`overlapping_parts()`], rather than of the entire images.
+    compensation: [isize; 2],
+
+    /// The color correction found and undone, if [`Differ::correct_color()`] was used.
+    color_correction: Option<ColorCorrection>,
+
+    /// Image quality metrics, if [`Differ::quality_metrics()`] was used.
+    quality_metrics: Option<QualityMetrics>,
+
+    /// Whether the diff image covers the entire input images, as set by
+    /// [`Differ::full_size_diff_image()`].
+    full_size_diff_image: bool,
+
+    /// Whether the edge maps of the images were compared instead of the images, as set by
+    /// [`Differ::compare_edges()`].
+    compared_edges: bool,
+}
+
+/// The per-pixel difference magnitudes stored in a [`Difference`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Magnitudes {
+    /// The images had different sizes, so no pixels were compared.
+    Mismatched,
+
+    /// The difference magnitude for each compared pixel.
+    /// Pixel (x, y) of these images corresponds to pixel (x + 1, y + 1) of the compared images,
+    /// because the edges are not compared.
+    Dense {
+        combined: ImgVec<u8>,
+        /// Indexed by [`Direction::index()`].
+        directional: [ImgVec<u8>; 2],
+    },
+
+    /// Only the pixels with nonzero differences, in row-major order and in the coordinates
+    /// of the compared images (which are the input images unless translation was compensated).
+    Sparse {
+        /// Size of the images that [`Magnitudes::Dense`] would have.
+        width: usize,
+        height: usize,
+        differences: Vec<PixelDifference>,
+    },
+}
+
+/// The difference found at a single pixel by a comparison.
+///
+/// Obtain these from [`Difference::differing_pixels()`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct PixelDifference {
+    /// X coordinate of the pixel in the input images.
+    pub x: usize,
+    /// Y coordinate of the pixel in the input images.
+    pub y: usize,
+    /// Magnitude of the difference, on the same scale as the [`Histogram`].
+    pub magnitude: u8,
+
+    /// Indexed by [`Direction::index()`].
+    directional: [u8; 2],
+}
+
+/// One of the two halves of the comparison [`diff()`] performs.
+///
+/// Each pixel of each image is compared to a neighborhood of the other image, and the
+/// final result is the maximum of the two.
+/// Examining only one direction of comparison tells you whether a difference is due to
+/// something being absent from the `actual` image or added to it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[allow(clippy::exhaustive_enums)]
+pub enum Direction {
+    /// Pixels of the `expected` image are compared to neighborhoods of the `actual` image;
+    /// large differences indicate content which is missing from `actual`.
+    Missing,
+    /// Pixels of the `actual` image are compared to neighborhoods of the `expected` image;
+    /// large differences indicate content which is extra in `actual`.
+    Extra,
+}
+
+impl Direction {
+    /// Index of this direction in arrays of per-direction data.
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Direction::Missing => 0,
+            Direction::Extra => 1,
+        }
+    }
 }
 
 impl Difference {
@@ -18,11 +119,93 @@ impl Difference {
         self.histogram
     }
 
+    /// A histogram of magnitudes of the differences detected in only one direction of
+    /// comparison.
+    ///
+    /// This may be used to define a pass/fail criterion which permits only one kind of
+    /// difference; for example, allowing an overlay to add content to the `actual` image,
+    /// but not to remove any of the `expected` content.
+    ///
+    /// If the images had different sizes, then this is the same as [`Difference::histogram()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Direction, Threshold};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+    /// let mut actual = expected.clone();
+    /// // Add a 2×2 white box, which is extra content.
+    /// for y in 3..5usize {
+    ///     for x in 3..5usize {
+    ///         actual[(x, y)] = [255, 255, 255, 255];
+    ///     }
+    /// }
+    ///
+    /// let difference = rendiff::diff(actual.as_ref(), expected.as_ref());
+    /// assert!(!Threshold::no_bigger_than(0).allows(difference.histogram()));
+    /// assert!(!Threshold::no_bigger_than(0).allows(difference.directional_histogram(Direction::Extra)));
+    /// assert!(Threshold::no_bigger_than(0).allows(difference.directional_histogram(Direction::Missing)));
+    /// ```
+    #[must_use]
+    pub fn directional_histogram(&self, direction: Direction) -> Histogram {
+        self.directional_histograms[direction.index()]
+    }
+
+    /// The magnitude of the difference at each compared pixel, or [`None`] if the images had
+    /// different sizes or the [`Differ`] was configured for [sparse](Differ::sparse) output.
+    ///
+    /// These are the exact values counted by [`Difference::histogram()`], unlike the
+    /// [`Difference::diff_image()`], which is intended for viewing.
+    /// The edges of the input images are not compared, so this image is smaller than the
+    /// inputs; pixel (x, y) of this image corresponds to pixel (x + 1, y + 1) of the input
+    /// images, or more generally, is offset by the position of
+    /// [`Difference::compared_area()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Rect};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+    /// let mut actual = expected.clone();
+    /// actual[(3usize, 6usize)] = [20, 20, 20, 255];
+    ///
+    /// let difference = rendiff::diff(actual.as_ref(), expected.as_ref());
+    /// let magnitudes = difference.magnitudes().unwrap();
+    /// assert_eq!(difference.compared_area(), Some(Rect::new(1, 1, 8, 8)));
+    /// assert_eq!((magnitudes.width(), magnitudes.height()), (8, 8));
+    /// assert_eq!(magnitudes[(2usize, 5usize)], 20);
+    /// ```
+    #[must_use]
+    pub fn magnitudes(&self) -> Option<ImgRef<'_, u8>> {
+        match &self.magnitudes {
+            Magnitudes::Dense { combined, .. } => Some(combined.as_ref()),
+            Magnitudes::Mismatched | Magnitudes::Sparse { .. } => None,
+        }
+    }
+
+    /// The magnitudes of the differences detected in only one direction of comparison,
+    /// for each compared pixel, or [`None`] if the images had different sizes or the
+    /// [`Differ`] was configured for [sparse](Differ::sparse) output.
+    ///
+    /// The coordinates are the same as those of [`Difference::magnitudes()`].
+    #[must_use]
+    pub fn directional_magnitudes(&self, direction: Direction) -> Option<ImgRef<'_, u8>> {
+        match &self.magnitudes {
+            Magnitudes::Dense { directional, .. } => Some(directional[direction.index()].as_ref()),
+            Magnitudes::Mismatched | Magnitudes::Sparse { .. } => None,
+        }
+    }
+
     /// An sRGB RGBA image intended for human viewing of which pixels are different,
-    /// or [`None`] if the images had different sizes.
+    /// or [`None`] if the images had different sizes or the [`Differ`] was configured
+    /// for [sparse](Differ::sparse) output.
     ///
     /// The precise content of this image is not specified. It will be 1:1 scale with the
-    /// images being compared, but it may be larger or smaller due to treatment of the edges.
+    /// images being compared, but it may be larger or smaller due to treatment of the edges,
+    /// unless [`Differ::full_size_diff_image()`] was used.
+    /// [`Difference::diff_image_area()`] tells which part of the input images it covers.
     ///
     /// Currently, the red channel contains data from the input `expected` image,
     /// and the blue and green channels contain differences, scaled up for high visibility.
@@ -30,6 +213,349 @@ impl Difference {
     pub fn diff_image(&self) -> Option<ImgRef<'_, RgbaPixel>> {
         self.diff_image.as_ref().map(imgref::ImgExt::as_ref)
     }
+
+    /// Creates the same image as [`Difference::diff_image()`] would return, even if this
+    /// [`Difference`] is [sparse](Differ::sparse) and therefore does not store it.
+    ///
+    /// `expected` must be the same `expected` image that was given to [`diff()`].
+    /// Returns [`None`] if the images had different sizes.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `expected` is not of the same size as the original.
+    #[must_use]
+    pub fn render_diff_image(&self, expected: ImgRef<'_, RgbaPixel>) -> Option<ImgVec<RgbaPixel>> {
+        if let Some(diff_image) = &self.diff_image {
+            return Some(diff_image.clone());
+        }
+        let raw_diff_image = self.magnitudes.to_dense()?;
+        Some(render_diff_image(
+            self.full_size_diff_image,
+            expected,
+            self.compensation,
+            raw_diff_image.as_ref(),
+            &self.histogram,
+            Vec::new(),
+        ))
+    }
+
+    /// Returns the rectangle of pixels of the input images which the
+    /// [diff image](Difference::diff_image) depicts, or [`None`] if the images had different
+    /// sizes.
+    ///
+    /// Pixel (x, y) of the diff image corresponds to pixel (x + area.x, y + area.y) of the
+    /// input images.
+    /// This is the same as [`Difference::compared_area()`], unless
+    /// [`Differ::full_size_diff_image()`] was used, in which case it is the entire images.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ, Rect};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 8], 10, 8);
+    /// let actual = expected.clone();
+    ///
+    /// let difference = Differ::new().compare(actual.as_ref(), expected.as_ref());
+    /// assert_eq!(difference.diff_image_area(), Some(Rect::new(1, 1, 8, 6)));
+    ///
+    /// let difference = Differ::new()
+    ///     .full_size_diff_image(true)
+    ///     .compare(actual.as_ref(), expected.as_ref());
+    /// assert_eq!(difference.diff_image_area(), Some(Rect::new(0, 0, 10, 8)));
+    /// assert_eq!(difference.diff_image().unwrap().width(), 10);
+    /// ```
+    #[must_use]
+    pub fn diff_image_area(&self) -> Option<Rect> {
+        if self.full_size_diff_image {
+            let [width, height] = self.input_size()?;
+            Some(Rect::new(0, 0, width, height))
+        } else {
+            self.compared_area()
+        }
+    }
+
+    /// Returns every pixel whose difference magnitude is not zero, in row-major order.
+    ///
+    /// This is available regardless of whether the [`Differ`] was configured for
+    /// [sparse](Differ::sparse) output, but is more efficient if it was.
+    /// If the images had different sizes, there are no pixels to report.
+    pub fn differing_pixels(&self) -> impl Iterator<Item = PixelDifference> + '_ {
+        let [origin_x, origin_y] = self.compared_origin();
+        let (dense, sparse) = match &self.magnitudes {
+            Magnitudes::Mismatched => (None, None),
+            Magnitudes::Dense {
+                combined,
+                directional,
+            } => (Some(dense_differing_pixels(combined, directional)), None),
+            Magnitudes::Sparse { differences, .. } => (None, Some(differences)),
+        };
+        dense
+            .into_iter()
+            .flatten()
+            .chain(sparse.into_iter().flatten().copied())
+            .map(move |d| PixelDifference {
+                x: d.x + origin_x,
+                y: d.y + origin_y,
+                ..d
+            })
+    }
+
+    /// Returns the translation of the `actual` image relative to the `expected` image which
+    /// was found by [`Differ::search_translation()`], or [`None`] if no search was performed.
+    ///
+    /// A translation of `[dx, dy]` means that the content of `expected` at `(x, y)` was found
+    /// in `actual` at `(x + dx, y + dy)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// let expected = ImgVec::new(
+    ///     (0..400).map(|i| [(i * 37 % 256) as u8, 0, 0, 255]).collect(),
+    ///     20,
+    ///     20,
+    /// );
+    /// // Shift the image 3 pixels to the right.
+    /// let mut actual = expected.clone();
+    /// for y in 0..20usize {
+    ///     for x in 3..20usize {
+    ///         actual[(x, y)] = expected[(x - 3, y)];
+    ///     }
+    /// }
+    ///
+    /// let difference = Differ::new()
+    ///     .search_translation(4)
+    ///     .compensate_translation(true)
+    ///     .compare(actual.as_ref(), expected.as_ref());
+    ///
+    /// // Shifted by (+3, 0), otherwise identical.
+    /// assert_eq!(difference.translation(), Some([3, 0]));
+    /// assert_eq!(difference.histogram().max_difference(), 0);
+    /// ```
+    #[must_use]
+    pub fn translation(&self) -> Option<[isize; 2]> {
+        self.translation
+    }
+
+    /// Returns the color correction which was fitted to the images and undone before
+    /// comparing them, if [`Differ::correct_color()`] was used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// let expected = ImgVec::new(
+    ///     (0..400).map(|i| {
+    ///         let value = (i % 20 * 10) as u8;
+    ///         [value, value, value, 255]
+    ///     }).collect(),
+    ///     20,
+    ///     20,
+    /// );
+    /// // Make the actual image brighter, and add a dot.
+    /// let mut actual = ImgVec::new(
+    ///     expected.pixels().map(|[r, g, b, a]| [r + 20, g + 20, b + 20, a]).collect(),
+    ///     20,
+    ///     20,
+    /// );
+    /// actual[(5usize, 5usize)] = [0, 0, 255, 255];
+    ///
+    /// let difference = Differ::new().correct_color(true).compare(actual.as_ref(), expected.as_ref());
+    ///
+    /// let correction = difference.color_correction().unwrap();
+    /// assert!((correction.offset[0] - 20.0).abs() < 0.1);
+    /// assert!((correction.gain[0] - 1.0).abs() < 0.01);
+    /// // Only the dot remains as a difference.
+    /// assert_eq!(
+    ///     difference.differing_pixels().map(|d| (d.x, d.y)).collect::<Vec<_>>(),
+    ///     vec![(5, 5)]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn color_correction(&self) -> Option<ColorCorrection> {
+        self.color_correction
+    }
+
+    /// Returns conventional image quality metrics (MSE, RMSE, and PSNR) of the comparison,
+    /// if [`Differ::quality_metrics()`] was enabled and the images had the same size.
+    #[must_use]
+    pub fn quality_metrics(&self) -> Option<QualityMetrics> {
+        self.quality_metrics
+    }
+
+    /// Returns the parts of the given images which were compared pixel-by-pixel,
+    /// after compensating for translation if applicable.
+    pub(crate) fn compared_parts<'a>(
+        &self,
+        actual: ImgRef<'a, RgbaPixel>,
+        expected: ImgRef<'a, RgbaPixel>,
+    ) -> [ImgRef<'a, RgbaPixel>; 2] {
+        overlapping_parts(actual, expected, self.compensation)
+    }
+
+    /// Calls `f` with the images which were actually compared pixel-by-pixel: the
+    /// [`compared_parts()`](Self::compared_parts) of the given images, after undoing the
+    /// [color correction](Differ::correct_color) and computing the
+    /// [edge maps](Differ::compare_edges) if those options were used.
+    pub(crate) fn with_compared_images<R>(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        f: impl FnOnce([ImgRef<'_, RgbaPixel>; 2]) -> R,
+    ) -> R {
+        let [actual, expected] = self.compared_parts(actual, expected);
+        let corrected = self
+            .color_correction
+            .map(|correction| correction.undo(actual, Vec::new()));
+        let actual = corrected
+            .as_ref()
+            .map_or(actual, |corrected| corrected.as_ref());
+        if self.compared_edges {
+            let [actual, expected] =
+                [actual, expected].map(|image| crate::edges::edge_map(image, Vec::new()));
+            f([actual.as_ref(), expected.as_ref()])
+        } else {
+            f([actual, expected])
+        }
+    }
+
+    /// Returns the rectangle of pixels of the input images which were compared,
+    /// or [`None`] if the images had different sizes.
+    ///
+    /// Pixel (x, y) of the [magnitude map](Difference::magnitudes) corresponds to pixel
+    /// (x + area.x, y + area.y) of the input images.
+    /// Ordinarily, this rectangle excludes only the 1-pixel edges of the images, which are
+    /// not compared because they do not have complete neighborhoods.
+    /// If [translation was compensated](Differ::compensate_translation), the rectangle also
+    /// excludes the parts of the `expected` image which did not overlap the `actual` image.
+    #[must_use]
+    pub fn compared_area(&self) -> Option<Rect> {
+        let [width, height] = self.magnitudes.size()?;
+        let [origin_x, origin_y] = self.compared_origin();
+        Some(Rect::new(origin_x + 1, origin_y + 1, width, height))
+    }
+
+    /// Returns the size of the `expected` image, if the images were compared.
+    pub(crate) fn input_size(&self) -> Option<[usize; 2]> {
+        let area = self.compared_area()?;
+        let [dx, dy] = self.compensation;
+        Some([
+            area.width + 2 + dx.unsigned_abs(),
+            area.height + 2 + dy.unsigned_abs(),
+        ])
+    }
+
+    /// Returns the position in the `expected` image of the parts returned by
+    /// [`Difference::compared_parts()`].
+    pub(crate) fn compared_origin(&self) -> [usize; 2] {
+        overlap_origin(self.compensation)
+    }
+
+    /// Returns whether the images had the same size, and therefore were compared pixel by pixel.
+    pub(crate) fn sizes_matched(&self) -> bool {
+        !matches!(self.magnitudes, Magnitudes::Mismatched)
+    }
+}
+
+impl PixelDifference {
+    /// Returns the magnitude of the difference at this pixel in only one direction
+    /// of comparison.
+    ///
+    /// See [`Difference::directional_histogram()`] for more information.
+    #[must_use]
+    pub fn directional_magnitude(&self, direction: Direction) -> u8 {
+        self.directional[direction.index()]
+    }
+}
+
+impl Magnitudes {
+    /// Returns the size of the dense image of magnitudes, whether or not it is stored.
+    fn size(&self) -> Option<[usize; 2]> {
+        match self {
+            Magnitudes::Mismatched => None,
+            Magnitudes::Dense { combined, .. } => Some(dimensions(combined.as_ref())),
+            &Magnitudes::Sparse { width, height, .. } => Some([width, height]),
+        }
+    }
+
+    /// Returns the dense image of combined magnitudes, converting from sparse if necessary.
+    fn to_dense(&self) -> Option<ImgVec<u8>> {
+        match self {
+            Magnitudes::Mismatched => None,
+            Magnitudes::Dense { combined, .. } => Some(combined.clone()),
+            &Magnitudes::Sparse {
+                width,
+                height,
+                ref differences,
+            } => {
+                let mut image = ImgVec::new(vec![0; width * height], width, height);
+                for d in differences {
+                    image[(d.x - 1, d.y - 1)] = d.magnitude;
+                }
+                Some(image)
+            }
+        }
+    }
+}
+
+/// Draws the diff image for the magnitudes `raw_diff_image`, which were computed from the part
+/// of `expected` selected by `compensation`.
+fn render_diff_image(
+    full_size: bool,
+    expected: ImgRef<'_, RgbaPixel>,
+    compensation: [isize; 2],
+    raw_diff_image: ImgRef<'_, u8>,
+    histogram: &Histogram,
+    buffer: Vec<RgbaPixel>,
+) -> ImgVec<RgbaPixel> {
+    if full_size {
+        let [origin_x, origin_y] = overlap_origin(compensation);
+        let compared_area = Rect::new(
+            origin_x + 1,
+            origin_y + 1,
+            raw_diff_image.width(),
+            raw_diff_image.height(),
+        );
+        crate::visualize::visualize_full_size(
+            expected,
+            compared_area,
+            raw_diff_image,
+            histogram,
+            buffer,
+        )
+    } else {
+        let [_, expected] = overlapping_parts(expected, expected, compensation);
+        crate::visualize::visualize(expected, raw_diff_image, histogram, buffer)
+    }
+}
+
+/// Implementation of [`Difference::differing_pixels()`] for [`Magnitudes::Dense`].
+fn dense_differing_pixels<'a>(
+    combined: &'a ImgVec<u8>,
+    [missing, extra]: &'a [ImgVec<u8>; 2],
+) -> impl Iterator<Item = PixelDifference> + 'a {
+    combined
+        .rows()
+        .zip(missing.rows().zip(extra.rows()))
+        .enumerate()
+        .flat_map(|(y, (combined_row, (missing_row, extra_row)))| {
+            combined_row
+                .iter()
+                .zip(missing_row.iter().zip(extra_row))
+                .enumerate()
+                .filter(|&(_, (&magnitude, _))| magnitude != 0)
+                .map(
+                    move |(x, (&magnitude, (&missing, &extra)))| PixelDifference {
+                        x: x + 1,
+                        y: y + 1,
+                        magnitude,
+                        directional: [missing, extra],
+                    },
+                )
+        })
 }
 
 /// Compares two RGBA images with a neighborhood-sensitive comparison which counts one pixel worth
@@ -43,109 +569,743 @@ impl Difference {
 /// Details:
 ///
 /// * If the images have different sizes, then the result will always be the maximum difference.
+///   The same is true if the images are smaller than 3×3 pixels, so that no pixel has a
+///   complete neighborhood to compare.
 /// * Differences in the alpha channel are counted the same as differences in luma; the maximum
 ///   of luma and alpha is used as the result.
+///
+/// This function is equivalent to `Differ::new().compare(actual, expected)`.
+/// If you are comparing many images, consider keeping a [`Differ`] instead,
+/// so that its scratch memory can be reused.
 #[must_use]
 pub fn diff(actual: ImgRef<'_, RgbaPixel>, expected: ImgRef<'_, RgbaPixel>) -> Difference {
-    if dimensions(expected) != dimensions(actual) {
-        return Difference {
-            // Count it as every pixel different.
-            histogram: {
-                let mut h = [0; 256];
-                h[usize::from(u8::MAX)] = expected.pixels().len().max(actual.pixels().len());
-                Histogram(h)
-            },
-            diff_image: None,
+    Differ::new().compare(actual, expected)
+}
+
+/// Reusable image comparison procedure.
+///
+/// A [`Differ`] holds the options for comparing images, and retains the memory it allocates
+/// for intermediate results so that it can be reused by the next comparison.
+/// This makes it more efficient than [`diff()`] when many images, particularly images of the
+/// same size, are to be compared.
+/// To also reuse the memory of the [`Difference`]s it returns, give them back to
+/// [`Differ::recycle()`] when you are done with them.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::{Differ, Threshold};
+/// # let frames: [(imgref::ImgVec<[u8; 4]>, imgref::ImgVec<[u8; 4]>); 0] = [];
+///
+/// let mut differ = Differ::new();
+/// for (actual, expected) in frames {
+///     let difference = differ.compare(actual.as_ref(), expected.as_ref());
+///     assert!(Threshold::no_bigger_than(2).allows(difference.histogram()));
+///     differ.recycle(difference);
+/// }
+/// ```
+#[derive(Clone, Default)]
+#[allow(clippy::struct_excessive_bools)] // they are independent options
+pub struct Differ {
+    sparse: bool,
+    translation_search: Option<usize>,
+    compensate_translation: bool,
+    correct_color: bool,
+    full_size_diff_image: bool,
+    quality_metrics: bool,
+    compare_edges: bool,
+
+    /// Buffers for differences computed by [`Differ::update()`] and
+    /// [`Differ::compare_pyramid()`],
+    /// in the same order as [`diff_region()`] uses.
+    pub(crate) region_buffers: [Vec<u8>; 3],
+
+    /// Allocations taken from [`Difference`]s given to [`Differ::recycle()`],
+    /// to be used for the next [`Difference`].
+    /// The magnitude buffers are also used as scratch space for sparse and pyramid comparisons.
+    pub(crate) spare_magnitude_buffers: [Vec<u8>; 3],
+    spare_diff_image_buffer: Vec<RgbaPixel>,
+
+    /// Buffer for the color-corrected `actual` image.
+    corrected_buffer: Vec<RgbaPixel>,
+
+    /// Buffers for the edge maps of the `actual` and `expected` images.
+    edge_buffers: [Vec<RgbaPixel>; 2],
+}
+
+impl Differ {
+    /// Creates a [`Differ`] with default options and no allocated memory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the [`Difference`]s produced should be sparse.
+    ///
+    /// A sparse [`Difference`] stores only the pixels which have nonzero differences,
+    /// and does not store a [diff image](Difference::diff_image), which saves memory
+    /// when comparing large images which are mostly identical.
+    /// The diff image can be created later using [`Difference::render_diff_image()`].
+    ///
+    /// The default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 200 * 200], 200, 200);
+    /// let mut actual = expected.clone();
+    /// actual[(150usize, 50usize)] = [255, 255, 255, 255];
+    ///
+    /// let difference = Differ::new().sparse(true).compare(actual.as_ref(), expected.as_ref());
+    /// assert_eq!(difference.diff_image(), None);
+    /// assert_eq!(
+    ///     difference.differing_pixels().map(|d| (d.x, d.y, d.magnitude)).collect::<Vec<_>>(),
+    ///     vec![(150, 50, 255)],
+    /// );
+    /// ```
+    #[must_use]
+    pub fn sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Sets whether to search for a translation of the entire `actual` image relative to the
+    /// `expected` image, of up to `max_offset` pixels horizontally and vertically, and
+    /// report it as [`Difference::translation()`].
+    ///
+    /// The translation found is the one which minimizes the average difference between
+    /// the overlapping parts of the images.
+    /// This is useful for diagnosing bugs which offset an entire image by a few pixels,
+    /// which would otherwise appear as differences at every edge.
+    /// The search takes time proportional to the size of the images times the square of
+    /// `max_offset`.
+    ///
+    /// The default is [`None`], which does not search.
+    #[must_use]
+    pub fn search_translation(mut self, max_offset: impl Into<Option<usize>>) -> Self {
+        self.translation_search = max_offset.into();
+        self
+    }
+
+    /// Sets whether, if a translation is found by [`Differ::search_translation()`], the
+    /// images should be compared after undoing it.
+    ///
+    /// If so, only the parts of the images which overlap after translation are compared,
+    /// and the diff image is correspondingly smaller.
+    /// Coordinates reported by [`Difference::differing_pixels()`] are still those of the
+    /// `expected` image.
+    ///
+    /// The default is `false`. Has no effect unless translation search is enabled.
+    #[must_use]
+    pub fn compensate_translation(mut self, compensate: bool) -> Self {
+        self.compensate_translation = compensate;
+        self
+    }
+
+    /// Sets whether to fit a [`ColorCorrection`] between the images, report it as
+    /// [`Difference::color_correction()`], and undo it before comparing them.
+    ///
+    /// This is useful for distinguishing a uniform change in brightness or contrast,
+    /// such as might be caused by a change of graphics driver, from localized errors,
+    /// which would otherwise be hidden among many small differences.
+    /// The correction is a gain and offset for each channel; nonlinear changes such as gamma
+    /// are only approximately corrected.
+    ///
+    /// The default is `false`.
+    #[must_use]
+    pub fn correct_color(mut self, correct: bool) -> Self {
+        self.correct_color = correct;
+        self
+    }
+
+    /// Sets whether the [diff image](Difference::diff_image) should be exactly the same size
+    /// as the input images, rather than only covering the pixels which were compared.
+    ///
+    /// If so, pixels which were not compared, such as the edges of the images, are drawn in
+    /// a distinct style (currently, dark blue), so that the diff image can be overlaid on the
+    /// input images without any adjustment of position.
+    ///
+    /// The default is `false`.
+    #[must_use]
+    pub fn full_size_diff_image(mut self, full_size: bool) -> Self {
+        self.full_size_diff_image = full_size;
+        self
+    }
+
+    /// Sets whether to compute [`QualityMetrics`] such as PSNR, and report them as
+    /// [`Difference::quality_metrics()`].
+    ///
+    /// This is useful for relating the results of this library to other tools which use
+    /// those metrics.
+    ///
+    /// The default is `false`.
+    #[must_use]
+    pub fn quality_metrics(mut self, enabled: bool) -> Self {
+        self.quality_metrics = enabled;
+        self
+    }
+
+    /// Sets whether to compare only the structure of the images, by comparing their
+    /// edge maps instead of their colors.
+    ///
+    /// If so, the [Sobel] gradient magnitudes of the luma and the alpha of each image are
+    /// computed, and the resulting grayscale images, whose pixels are the larger of the two
+    /// magnitudes, are compared in the usual way.
+    /// Thus, a difference only in alpha is found only where it changes the edges of
+    /// transparency, just as a difference in color is found only where it changes the
+    /// edges of luma.
+    /// This is useful for stylized rendering whose fill colors may legitimately vary,
+    /// such as with a change of theme, while the geometry should not;
+    /// a change of color is then only a difference to the extent that it changes the
+    /// contrast of the edges.
+    /// The [diff image](Difference::diff_image) still shows the original `expected` image,
+    /// and the raw [quality metrics](Differ::quality_metrics) still compare the original
+    /// images, but the difference magnitudes, and everything computed from them, are of
+    /// the edge maps.
+    ///
+    /// The default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// // A square, drawn in different colors.
+    /// let square = |color: [u8; 4]| {
+    ///     let mut image = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+    ///     for y in 3..7usize {
+    ///         for x in 3..7usize {
+    ///             image[(x, y)] = color;
+    ///         }
+    ///     }
+    ///     image
+    /// };
+    /// // These colors have nearly the same luma.
+    /// let expected = square([150, 150, 150, 255]);
+    /// let actual = square([0, 200, 255, 255]);
+    ///
+    /// assert!(rendiff::diff(actual.as_ref(), expected.as_ref()).histogram().max_difference() > 50);
+    /// let difference = Differ::new()
+    ///     .compare_edges(true)
+    ///     .compare(actual.as_ref(), expected.as_ref());
+    /// assert!(difference.histogram().max_difference() < 50);
+    /// ```
+    ///
+    /// [Sobel]: https://en.wikipedia.org/wiki/Sobel_operator
+    #[must_use]
+    pub fn compare_edges(mut self, compare_edges: bool) -> Self {
+        self.compare_edges = compare_edges;
+        self
+    }
+
+    /// Compares two RGBA images, exactly as [`diff()`] does
+    /// (except as modified by the options set on this [`Differ`]).
+    ///
+    /// See [`diff()`] for details.
+    #[must_use]
+    pub fn compare(
+        &mut self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+    ) -> Difference {
+        if dimensions(expected) != dimensions(actual) {
+            return self.mismatched_sizes(actual, expected);
+        }
+
+        let (translation, compensation) = self.find_translation(actual, expected);
+        let input_expected = expected;
+        let [actual, expected] = match overlapping_parts(actual, expected, compensation) {
+            // If there are no pixels with complete neighborhoods, nothing can be compared.
+            [_, part] if part.width() < 3 || part.height() < 3 => {
+                return self.mismatched_sizes(actual, expected);
+            }
+            parts => parts,
+        };
+
+        let color_correction = self
+            .correct_color
+            .then(|| ColorCorrection::fit(actual, expected));
+        let corrected =
+            color_correction.map(|c| c.undo(actual, mem::take(&mut self.corrected_buffer)));
+        let actual = corrected
+            .as_ref()
+            .map_or(actual, |corrected| corrected.as_ref());
+
+        let edges = self.compare_edges.then(|| {
+            let [actual_buffer, expected_buffer] = mem::take(&mut self.edge_buffers);
+            [
+                crate::edges::edge_map(actual, actual_buffer),
+                crate::edges::edge_map(expected, expected_buffer),
+            ]
+        });
+        let [compared_actual, compared_expected] =
+            edges.as_ref().map_or([actual, expected], |edges| {
+                [edges[0].as_ref(), edges[1].as_ref()]
+            });
+
+        let interior = interior(expected);
+        let mut buffers = mem::take(&mut self.spare_magnitude_buffers);
+        for buffer in &mut buffers {
+            buffer.clear();
+        }
+        diff_region(compared_actual, compared_expected, interior, &mut buffers);
+        let [combined, missing, extra] =
+            buffers.map(|buffer| ImgVec::new(buffer, interior.width, interior.height));
+        let directional = [missing, extra];
+
+        // Compute histograms of difference sizes.
+        let histogram = histogram_of(combined.as_ref());
+        let directional_histograms = [
+            histogram_of(directional[0].as_ref()),
+            histogram_of(directional[1].as_ref()),
+        ];
+        let quality_metrics = self
+            .quality_metrics
+            .then(|| QualityMetrics::compute(actual, expected, &histogram));
+
+        let difference = if self.sparse {
+            let differences = dense_differing_pixels(&combined, &directional).collect();
+            // The dense images were only scratch space, so keep them for next time.
+            let [missing, extra] = directional;
+            self.spare_magnitude_buffers = [combined, missing, extra].map(ImgVec::into_buf);
+            Difference {
+                histogram,
+                directional_histograms,
+                magnitudes: Magnitudes::Sparse {
+                    width: interior.width,
+                    height: interior.height,
+                    differences,
+                },
+                diff_image: None,
+                translation,
+                compensation,
+                color_correction,
+                quality_metrics,
+                full_size_diff_image: self.full_size_diff_image,
+                compared_edges: self.compare_edges,
+            }
+        } else {
+            Difference {
+                histogram,
+                directional_histograms,
+                diff_image: Some(render_diff_image(
+                    self.full_size_diff_image,
+                    input_expected,
+                    compensation,
+                    combined.as_ref(),
+                    &histogram,
+                    mem::take(&mut self.spare_diff_image_buffer),
+                )),
+                magnitudes: Magnitudes::Dense {
+                    combined,
+                    directional,
+                },
+                translation,
+                compensation,
+                color_correction,
+                quality_metrics,
+                full_size_diff_image: self.full_size_diff_image,
+                compared_edges: self.compare_edges,
+            }
         };
+        if let Some(corrected) = corrected {
+            self.corrected_buffer = corrected.into_buf();
+        }
+        if let Some(edges) = edges {
+            self.edge_buffers = edges.map(ImgVec::into_buf);
+        }
+        difference
     }
 
-    let hd1 = half_diff(expected, actual);
-    let hd2 = half_diff(actual, expected);
+    /// Returns the translation found, if translation search is enabled, and the translation
+    /// which should be compensated for.
+    fn find_translation(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+    ) -> (Option<[isize; 2]>, [isize; 2]) {
+        let translation = self
+            .translation_search
+            .map(|max_offset| crate::translation::find_translation(actual, expected, max_offset));
+        let compensation = match translation {
+            Some(translation) if self.compensate_translation => translation,
+            _ => [0, 0],
+        };
+        (translation, compensation)
+    }
 
-    // Combine the two half_diff results: _both_ must be small for the output to be small.
-    let raw_diff_image: ImgVec<u8> = ImgVec::new(
-        (0..hd1.height())
-            .flat_map(|y| {
-                (0..hd1.width()).map({
-                    let hd1 = &hd1;
-                    let hd2 = &hd2;
-                    move |x| core::cmp::max(hd1[(x, y)], hd2[(x, y)])
-                })
-            })
-            .collect(),
-        hd1.width(),
-        hd1.height(),
-    );
+    /// Returns the [`Difference`] of images which have different sizes, or are too small to
+    /// compare.
+    pub(crate) fn mismatched_sizes(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+    ) -> Difference {
+        // Count it as every pixel different.
+        let histogram = {
+            let mut h = [0; 256];
+            h[usize::from(u8::MAX)] = expected.pixels().len().max(actual.pixels().len());
+            Histogram(h)
+        };
+        Difference {
+            histogram,
+            directional_histograms: [histogram; 2],
+            magnitudes: Magnitudes::Mismatched,
+            diff_image: None,
+            translation: None,
+            compensation: [0, 0],
+            color_correction: None,
+            quality_metrics: None,
+            full_size_diff_image: self.full_size_diff_image,
+            compared_edges: false,
+        }
+    }
 
-    // Compute a histogram of difference sizes.
-    let mut histogram: [usize; 256] = [0; 256];
-    for diff_value in raw_diff_image.pixels() {
-        histogram[usize::from(diff_value)] += 1;
+    /// Updates `difference` to be the result of comparing `actual` and `expected`,
+    /// given that they are unchanged from the images it was previously computed from,
+    /// except for the pixels within `dirty`.
+    ///
+    /// This takes time proportional to the size of `dirty`, rather than of the entire images
+    /// (except when the maximum difference changes, which requires the entire diff image to be
+    /// rescaled, and except for sorting the differences if the [`Difference`] is sparse).
+    ///
+    /// `difference` should have been produced by this [`Differ`] or one with the same options.
+    /// If `actual` and `expected` differ from the previously compared images outside of
+    /// `dirty`, then the result will be incorrect (but not undefined behavior).
+    /// If the sizes of the images differ from each other or from the previous images,
+    /// or [translation search](Differ::search_translation),
+    /// [color correction](Differ::correct_color),
+    /// [quality metrics](Differ::quality_metrics), or
+    /// [edge comparison](Differ::compare_edges) are enabled,
+    /// the comparison is done from scratch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ, Rect};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 100 * 100], 100, 100);
+    /// let mut actual = expected.clone();
+    ///
+    /// let mut differ = Differ::new();
+    /// let mut difference = differ.compare(actual.as_ref(), expected.as_ref());
+    /// assert_eq!(difference.histogram().max_difference(), 0);
+    ///
+    /// actual[(50usize, 50usize)] = [255, 255, 255, 255];
+    /// differ.update(&mut difference, actual.as_ref(), expected.as_ref(), Rect::new(50, 50, 1, 1));
+    /// assert_eq!(difference, differ.compare(actual.as_ref(), expected.as_ref()));
+    /// ```
+    pub fn update(
+        &mut self,
+        difference: &mut Difference,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        dirty: Rect,
+    ) {
+        let interior = interior(expected);
+        if dimensions(expected) != dimensions(actual)
+            || difference.magnitudes.size() != Some([interior.width, interior.height])
+            || self.has_whole_image_options()
+        {
+            *difference = self.compare(actual, expected);
+            return;
+        }
+
+        // Every pixel whose neighborhood includes a dirty pixel must be recomputed.
+        let region = dirty.expand(1).intersection(interior);
+        if region.is_empty() {
+            return;
+        }
+        let mut new_values = mem::take(&mut self.region_buffers);
+        for buffer in &mut new_values {
+            buffer.clear();
+        }
+        diff_region(actual, expected, region, &mut new_values);
+
+        // Replace the old values and their histogram counts with the new ones.
+        let old_max_difference = difference.histogram.max_difference();
+        let [new_combined, new_missing, new_extra] = &new_values;
+        match &mut difference.magnitudes {
+            Magnitudes::Mismatched => unreachable!("size was checked"),
+            Magnitudes::Dense {
+                combined,
+                directional: [missing, extra],
+            } => {
+                let region_in_raw =
+                    Rect::new(region.x - 1, region.y - 1, region.width, region.height);
+                let [missing_histogram, extra_histogram] = &mut difference.directional_histograms;
+                replace_region(
+                    combined,
+                    region_in_raw,
+                    new_combined,
+                    &mut difference.histogram,
+                );
+                replace_region(missing, region_in_raw, new_missing, missing_histogram);
+                replace_region(extra, region_in_raw, new_extra, extra_histogram);
+
+                // The diff image's scaling depends on the maximum difference, so if that changed,
+                // the whole image must be redrawn.
+                if let Some(diff_image) = &mut difference.diff_image {
+                    // Without translation compensation, the compared area is the interior.
+                    let origin = usize::from(difference.full_size_diff_image);
+                    let (width, height) = (combined.width(), combined.height());
+                    crate::visualize::visualize_region(
+                        expected,
+                        combined.as_ref(),
+                        &difference.histogram,
+                        if difference.histogram.max_difference() == old_max_difference {
+                            region_in_raw
+                        } else {
+                            Rect::new(0, 0, width, height)
+                        },
+                        diff_image.sub_image_mut(origin, origin, width, height),
+                    );
+                }
+            }
+            Magnitudes::Sparse { differences, .. } => {
+                let mut histograms = {
+                    let [missing_histogram, extra_histogram] =
+                        &mut difference.directional_histograms;
+                    [
+                        &mut difference.histogram,
+                        missing_histogram,
+                        extra_histogram,
+                    ]
+                };
+                let mut old_nonzero_count = 0;
+                differences.retain(|d| {
+                    let in_region = region.contains(d.x, d.y);
+                    if in_region {
+                        for (histogram, value) in histograms.iter_mut().zip(sparse_values(d)) {
+                            histogram.0[usize::from(value)] -= 1;
+                        }
+                        old_nonzero_count += 1;
+                    }
+                    !in_region
+                });
+                for histogram in &mut histograms {
+                    histogram.0[0] -= region.area() - old_nonzero_count;
+                }
+
+                let mut new_values = new_combined.iter().zip(new_missing.iter().zip(new_extra));
+                for y in region.y..region.y_end() {
+                    for (x, (&magnitude, (&missing, &extra))) in
+                        (region.x..region.x_end()).zip(&mut new_values)
+                    {
+                        let d = PixelDifference {
+                            x,
+                            y,
+                            magnitude,
+                            directional: [missing, extra],
+                        };
+                        for (histogram, value) in histograms.iter_mut().zip(sparse_values(&d)) {
+                            histogram.0[usize::from(value)] += 1;
+                        }
+                        if magnitude != 0 {
+                            differences.push(d);
+                        }
+                    }
+                }
+                differences.sort_unstable_by_key(|d| (d.y, d.x));
+            }
+        }
+        self.region_buffers = new_values;
     }
-    let histogram = Histogram(histogram);
 
-    Difference {
-        histogram,
-        diff_image: Some(crate::visualize::visualize(
-            expected,
-            raw_diff_image.as_ref(),
-            &histogram,
-        )),
+    /// Returns whether any options are enabled which prevent [`Differ::update()`] from
+    /// comparing only the changed region.
+    fn has_whole_image_options(&self) -> bool {
+        self.translation_search.is_some()
+            || self.correct_color
+            || self.quality_metrics
+            || self.compare_edges
+    }
+
+    /// Takes the memory allocated for `difference`, so that it can be reused by the next
+    /// comparison this [`Differ`] performs.
+    pub fn recycle(&mut self, difference: Difference) {
+        let Difference {
+            histogram: _,
+            directional_histograms: _,
+            magnitudes,
+            diff_image,
+            translation: _,
+            compensation: _,
+            color_correction: _,
+            quality_metrics: _,
+            full_size_diff_image: _,
+            compared_edges: _,
+        } = difference;
+        if let Magnitudes::Dense {
+            combined,
+            directional: [missing, extra],
+        } = magnitudes
+        {
+            self.spare_magnitude_buffers = [combined, missing, extra].map(ImgVec::into_buf);
+        }
+        if let Some(image) = diff_image {
+            self.spare_diff_image_buffer = image.into_buf();
+        }
+    }
+}
+
+impl fmt::Debug for Differ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the scratch buffers; they are not meaningful.
+        f.debug_struct("Differ")
+            .field("sparse", &self.sparse)
+            .field("translation_search", &self.translation_search)
+            .field("compensate_translation", &self.compensate_translation)
+            .field("correct_color", &self.correct_color)
+            .field("full_size_diff_image", &self.full_size_diff_image)
+            .field("quality_metrics", &self.quality_metrics)
+            .field("compare_edges", &self.compare_edges)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the values of a sparse difference in the same order as [`diff_region()`] uses.
+fn sparse_values(d: &PixelDifference) -> [u8; 3] {
+    let [missing, extra] = d.directional;
+    [d.magnitude, missing, extra]
+}
+
+/// Replaces the values of `image` within `region` with `new_values`, and updates `histogram`
+/// to match.
+fn replace_region(
+    image: &mut ImgVec<u8>,
+    region: Rect,
+    new_values: &[u8],
+    histogram: &mut Histogram,
+) {
+    for (old_value, &new_value) in image
+        .sub_image_mut(region.x, region.y, region.width, region.height)
+        .pixels_mut()
+        .zip(new_values)
+    {
+        histogram.0[usize::from(*old_value)] -= 1;
+        histogram.0[usize::from(new_value)] += 1;
+        *old_value = new_value;
     }
 }
 
-fn dimensions<T>(image: imgref::ImgRef<'_, T>) -> [usize; 2] {
+pub(crate) fn histogram_of(image: ImgRef<'_, u8>) -> Histogram {
+    let mut histogram: [usize; 256] = [0; 256];
+    for diff_value in image.pixels() {
+        histogram[usize::from(diff_value)] += 1;
+    }
+    Histogram(histogram)
+}
+
+pub(crate) fn dimensions<T>(image: imgref::ImgRef<'_, T>) -> [usize; 2] {
     [image.width(), image.height()]
 }
 
-/// Compare each pixel of `have` against a neighborhood of `want` (ignoring the edge).
+/// Returns the region of `image` which is compared; that is, all but the edges.
+/// If the image is smaller than 3×3, this is empty.
+pub(crate) fn interior<T>(image: ImgRef<'_, T>) -> Rect {
+    Rect::new(
+        1,
+        1,
+        image.width().saturating_sub(2),
+        image.height().saturating_sub(2),
+    )
+}
+
+/// Computes the difference values for the pixels of `region` (in input image coordinates,
+/// and not including the edges of the images), appending them in row-major order to
+/// `outputs`, which are the combined differences and the differences in each [`Direction`].
+pub(crate) fn diff_region(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    region: Rect,
+    [combined, missing, extra]: &mut [Vec<u8>; 3],
+) {
+    let missing_start = missing.len();
+    let extra_start = extra.len();
+    half_diff(expected, actual, region, missing);
+    half_diff(actual, expected, region, extra);
+
+    // Combine the two half_diff results: _both_ must be small for the output to be small.
+    combined.extend(
+        missing[missing_start..]
+            .iter()
+            .zip(&extra[extra_start..])
+            .map(|(&d1, &d2)| core::cmp::max(d1, d2)),
+    );
+}
+
+/// Compare each pixel of `have` within `region` against a neighborhood of `want`.
 /// Each pixel's color must be approximately equal to some pixel in the neighborhood.
+/// `region` must not include the edges of the images, since those have incomplete
+/// neighborhoods.
 ///
 /// This is "half" of the complete diffing process because the neighborhood comparison
 /// could allow a 1-pixel line in `want` to completely vanish. By performing the same
 /// comparison in both directions, we ensure that each color in each image must also
 /// appear in the other image.
-fn half_diff(have: ImgRef<'_, RgbaPixel>, want: ImgRef<'_, RgbaPixel>) -> ImgVec<u8> {
-    let have_elems = have.sub_image(1, 1, have.width() - 2, have.height() - 2);
+///
+/// The result is appended to `buffer` in row-major order.
+fn half_diff(
+    have: ImgRef<'_, RgbaPixel>,
+    want: ImgRef<'_, RgbaPixel>,
+    region: Rect,
+    buffer: &mut Vec<u8>,
+) {
+    let have_elems = region.sub_image(have);
+    let want_elems = region.expand(1).sub_image(want);
 
-    let mut buffer: Vec<u8> = vec_for_same_size_image(have);
+    buffer.reserve(region.area());
     for (y, have_row) in have_elems.rows().enumerate() {
         // Precalculate the rows in `want` that we're going to be fetching neighborhoods from.
         let want_rows: [&[RgbaPixel]; 3] = {
             // The row iterator overrides nth() which makes skip() O(1).
-            let mut iter = want.rows().skip(y);
+            let mut iter = want_elems.rows().skip(y);
             std::array::from_fn(|_| iter.next().unwrap_or(/* unreachable */ &[]))
         };
 
-        buffer.extend(have_row.iter().enumerate().map(move |(x, &have_pixel)| {
-            // Note on coordinates:
-            // The x and y we get from the enumerate()s start at (0, 0) ignoring our offset,
-            // so when we use those same x,y as top-left corner of the neighborhood,
-            // we get a centered neighborhood.
-            //
-            // Note on performance: this mess of explicit indexing proved faster than
-            // `want.sub_image().pixels()`, and also faster than iterating over slices.
-            let neighborhood = [
-                want_rows[0][x],
-                want_rows[0][x + 1],
-                want_rows[0][x + 2],
-                want_rows[1][x],
-                want_rows[1][x + 1],
-                want_rows[1][x + 2],
-                want_rows[2][x],
-                want_rows[2][x + 1],
-                want_rows[2][x + 2],
-            ];
-            let minimum_diff_in_neighborhood: u8 = neighborhood
-                .into_iter()
-                .map(|want_pixel| pixel_diff(have_pixel, want_pixel))
-                .min()
-                .expect("neighborhood is never empty");
-            minimum_diff_in_neighborhood
-        }));
-    }
-
-    ImgVec::new(buffer, have_elems.width(), have_elems.height())
+        half_diff_row(have_row, want_rows, buffer);
+    }
+}
+
+/// Computes one row of [`half_diff()`] output, appending it to `output`.
+///
+/// `want_rows` are the three rows of `want` centered on `have_row`, and must each be
+/// 2 pixels wider than `have_row`, so that `want_rows[1][x + 1]` is the pixel corresponding
+/// to `have_row[x]`.
+pub(crate) fn half_diff_row(
+    have_row: &[RgbaPixel],
+    want_rows: [&[RgbaPixel]; 3],
+    output: &mut Vec<u8>,
+) {
+    output.extend(have_row.iter().enumerate().map(move |(x, &have_pixel)| {
+        // Note on coordinates:
+        // The x and y we get from the enumerate()s start at (0, 0) ignoring our offset,
+        // so when we use those same x,y as top-left corner of the neighborhood,
+        // we get a centered neighborhood.
+        //
+        // Note on performance: this mess of explicit indexing proved faster than
+        // `want.sub_image().pixels()`, and also faster than iterating over slices.
+        let neighborhood = [
+            want_rows[0][x],
+            want_rows[0][x + 1],
+            want_rows[0][x + 2],
+            want_rows[1][x],
+            want_rows[1][x + 1],
+            want_rows[1][x + 2],
+            want_rows[2][x],
+            want_rows[2][x + 1],
+            want_rows[2][x + 2],
+        ];
+        let minimum_diff_in_neighborhood: u8 = neighborhood
+            .into_iter()
+            .map(|want_pixel| pixel_diff(have_pixel, want_pixel))
+            .min()
+            .expect("neighborhood is never empty");
+        minimum_diff_in_neighborhood
+    }));
 }
 
 /// Compare two pixel values and produce a difference magnitude.
@@ -153,7 +1313,7 @@ fn half_diff(have: ImgRef<'_, RgbaPixel>, want: ImgRef<'_, RgbaPixel>) -> ImgVec
 /// TODO: This function should be replaceable by the caller of `diff()` instead,
 /// allowing the caller to choose a perceptual or encoded difference function,
 /// and choose how they wish to treat alpha.
-fn pixel_diff(a: RgbaPixel, b: RgbaPixel) -> u8 {
+pub(crate) fn pixel_diff(a: RgbaPixel, b: RgbaPixel) -> u8 {
     // Diff each channel independently, then convert the difference to luma.
     // Note: this is a very naive comparison, but
     let r_diff = a[0].abs_diff(b[0]);
@@ -166,12 +1326,6 @@ fn pixel_diff(a: RgbaPixel, b: RgbaPixel) -> u8 {
     color_diff.max(a_diff)
 }
 
-#[cfg_attr(test, mutants::skip)] // Not really testable.
-fn vec_for_same_size_image<T>(image: ImgRef<'_, impl Sized>) -> Vec<T> {
-    // We should not use `image.buf().len()` because that includes stride.
-    Vec::with_capacity(image.width() * image.height())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,6 +1387,8 @@ mod tests {
 
         let mut expected_histogram = [0; 256];
         expected_histogram[usize::from(dred)] = 1;
+        let mut zero_histogram = [0; 256];
+        zero_histogram[0] = 1;
 
         // Try both orders; result should be symmetric except for the diff image
         let result_of_negative_difference = dbg!(diff_vecs(
@@ -263,22 +1419,44 @@ mod tests {
             result_of_positive_difference,
             Difference {
                 histogram: Histogram(expected_histogram),
+                directional_histograms: [Histogram(zero_histogram), Histogram(expected_histogram)],
+                magnitudes: Magnitudes::Dense {
+                    combined: ImgVec::new(vec![dred], 1, 1),
+                    directional: [ImgVec::new(vec![0], 1, 1), ImgVec::new(vec![dred], 1, 1)],
+                },
                 diff_image: Some(ImgVec::new(
                     vec![[(base_pixel_value) / display_scale, 255, 255, 255]],
                     1,
                     1,
-                ))
+                )),
+                translation: None,
+                compensation: [0, 0],
+                color_correction: None,
+                quality_metrics: None,
+                full_size_diff_image: false,
+                compared_edges: false,
             }
         );
         assert_eq!(
             result_of_negative_difference,
             Difference {
                 histogram: Histogram(expected_histogram),
+                directional_histograms: [Histogram(expected_histogram), Histogram(zero_histogram)],
+                magnitudes: Magnitudes::Dense {
+                    combined: ImgVec::new(vec![dred], 1, 1),
+                    directional: [ImgVec::new(vec![dred], 1, 1), ImgVec::new(vec![0], 1, 1)],
+                },
                 diff_image: Some(ImgVec::new(
                     vec![[(base_pixel_value + dred) / display_scale, 255, 255, 255]],
                     1,
                     1,
-                ))
+                )),
+                translation: None,
+                compensation: [0, 0],
+                color_correction: None,
+                quality_metrics: None,
+                full_size_diff_image: false,
+                compared_edges: false,
             }
         );
 
@@ -317,9 +1495,347 @@ mod tests {
                     h[255] = 2;
                     Histogram(h)
                 },
-                diff_image: None
+                directional_histograms: [{
+                    let mut h = [0; 256];
+                    h[255] = 2;
+                    Histogram(h)
+                }; 2],
+                magnitudes: Magnitudes::Mismatched,
+                diff_image: None,
+                translation: None,
+                compensation: [0, 0],
+                color_correction: None,
+                quality_metrics: None,
+                full_size_diff_image: false,
+                compared_edges: false,
+            }
+        );
+    }
+
+    /// A [`Differ`] which is reused for several comparisons, including of different sizes,
+    /// should give the same results as fresh ones.
+    #[test]
+    fn differ_reuse() {
+        let image_a = crate::image::from_fn(10, 7, |x, y| {
+            luma_to_rgba(u8::try_from(x * 20 + y).unwrap())
+        });
+        let image_b = crate::image::from_fn(10, 7, |x, y| {
+            luma_to_rgba(u8::try_from(x * 20 + y * 3).unwrap())
+        });
+        let image_c =
+            crate::image::from_fn(4, 5, |x, y| luma_to_rgba(u8::try_from(x + y).unwrap()));
+        let image_d = crate::image::from_fn(4, 5, |_, _| luma_to_rgba(200));
+
+        let mut differ = Differ::new();
+        for (actual, expected) in [
+            (&image_a, &image_b),
+            (&image_c, &image_d),
+            (&image_b, &image_a),
+            (&image_a, &image_c),
+            (&image_d, &image_c),
+        ] {
+            let difference = differ.compare(actual.as_ref(), expected.as_ref());
+            assert_eq!(difference, diff(actual.as_ref(), expected.as_ref()));
+            differ.recycle(difference);
+        }
+    }
+
+    /// [`Differ::update()`] should give the same results as comparing from scratch,
+    /// including when the maximum difference changes and when the dirty rectangle
+    /// touches or exceeds the edges.
+    #[test]
+    fn update_matches_compare() {
+        for sparse in [false, true] {
+            let expected = crate::image::from_fn(12, 10, |x, y| luma_to_rgba(u8::from(x > y) * 50));
+            let mut actual = expected.clone();
+            let mut differ = Differ::new().sparse(sparse);
+            let mut difference = differ.compare(actual.as_ref(), expected.as_ref());
+
+            for (dirty, value) in [
+                (Rect::new(3, 3, 2, 2), 10),
+                (Rect::new(0, 0, 1, 1), 200),
+                (Rect::new(3, 3, 1, 1), 0),
+                (Rect::new(8, 6, 10, 10), 100),
+                (Rect::new(8, 6, 10, 10), 50),
+                (Rect::new(0, 0, 12, 10), 7),
+                // Rectangles extending past the images, even to the limits of `usize`.
+                (Rect::new(usize::MAX - 1, 0, 5, 5), 30),
+                (Rect::new(10, 8, usize::MAX, usize::MAX), 90),
+            ] {
+                for y in dirty.y..dirty.y_end().min(actual.height()) {
+                    for x in dirty.x..dirty.x_end().min(actual.width()) {
+                        actual[(x, y)] = luma_to_rgba(value);
+                    }
+                }
+                differ.update(&mut difference, actual.as_ref(), expected.as_ref(), dirty);
+                assert_eq!(
+                    difference,
+                    differ.compare(actual.as_ref(), expected.as_ref()),
+                    "after changing {dirty:?} to {value} with sparse = {sparse}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_equivalent_to_dense() {
+        let expected = crate::image::from_fn(12, 10, |x, y| luma_to_rgba(u8::from(x > y) * 50));
+        let actual = crate::image::from_fn(12, 10, |x, y| {
+            luma_to_rgba(u8::from(x > y + 1) * 50 + u8::from(x == 5 && y == 2) * 9)
+        });
+        let dense = Differ::new().compare(actual.as_ref(), expected.as_ref());
+        let sparse = Differ::new()
+            .sparse(true)
+            .compare(actual.as_ref(), expected.as_ref());
+
+        assert_eq!(sparse.histogram(), dense.histogram());
+        assert_eq!(sparse.diff_image(), None);
+        assert_eq!(
+            sparse.differing_pixels().collect::<Vec<_>>(),
+            dense.differing_pixels().collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            sparse.render_diff_image(expected.as_ref()).unwrap(),
+            dense.diff_image().unwrap()
+        );
+        assert!(sparse.differing_pixels().count() > 0);
+        assert!(matches!(sparse.magnitudes, Magnitudes::Sparse { .. }));
+    }
+
+    #[test]
+    fn directions_of_added_and_removed_content() {
+        let background = crate::image::from_fn(8, 8, |_, _| luma_to_rgba(0));
+        let with_dot =
+            crate::image::from_fn(8, 8, |x, y| luma_to_rgba(u8::from((x, y) == (4, 3)) * 80));
+
+        for sparse in [false, true] {
+            let mut differ = Differ::new().sparse(sparse);
+            let added = differ.compare(with_dot.as_ref(), background.as_ref());
+            let removed = differ.compare(background.as_ref(), with_dot.as_ref());
+
+            assert_eq!(
+                added
+                    .directional_histogram(Direction::Missing)
+                    .max_difference(),
+                0
+            );
+            assert_eq!(
+                added
+                    .directional_histogram(Direction::Extra)
+                    .max_difference(),
+                80
+            );
+            assert_eq!(
+                removed
+                    .directional_histogram(Direction::Missing)
+                    .max_difference(),
+                80
+            );
+            assert_eq!(
+                removed
+                    .directional_histogram(Direction::Extra)
+                    .max_difference(),
+                0
+            );
+
+            let pixel = added.differing_pixels().next().unwrap();
+            assert_eq!((pixel.x, pixel.y), (4, 3));
+            assert_eq!(pixel.directional_magnitude(Direction::Missing), 0);
+            assert_eq!(pixel.directional_magnitude(Direction::Extra), 80);
+
+            if sparse {
+                assert_eq!(added.directional_magnitudes(Direction::Extra), None);
+            } else {
+                let extra = added.directional_magnitudes(Direction::Extra).unwrap();
+                assert_eq!(extra[(3usize, 2usize)], 80);
+                assert!(added
+                    .directional_magnitudes(Direction::Missing)
+                    .unwrap()
+                    .pixels()
+                    .all(|value| value == 0));
             }
+        }
+    }
+
+    #[test]
+    fn translation_compensation() {
+        let pattern =
+            |x: usize, y: usize| luma_to_rgba(u8::try_from((x * 5 + y * 11) % 50).unwrap());
+        let expected = crate::image::from_fn(16, 12, pattern);
+        // Shifted by (-2, +1), with one extra dot.
+        let actual = crate::image::from_fn(16, 12, |x, y| {
+            if (x, y) == (6, 7) {
+                [255, 255, 255, 255]
+            } else {
+                pattern(x + 2, y + 49)
+            }
+        });
+
+        for sparse in [false, true] {
+            let mut differ = Differ::new().sparse(sparse).search_translation(3);
+            let uncompensated = differ.compare(actual.as_ref(), expected.as_ref());
+            assert_eq!(uncompensated.translation(), Some([-2, 1]));
+            assert!(uncompensated.differing_pixels().count() > 50);
+
+            let mut differ = differ.compensate_translation(true);
+            let compensated = differ.compare(actual.as_ref(), expected.as_ref());
+            assert_eq!(compensated.translation(), Some([-2, 1]));
+            assert_eq!(compensated.compared_area(), Some(Rect::new(3, 1, 12, 9)));
+            // The dot in actual corresponds to (8, 6) in expected.
+            assert_eq!(
+                compensated
+                    .differing_pixels()
+                    .map(|d| (d.x, d.y))
+                    .collect::<Vec<_>>(),
+                vec![(8, 6)]
+            );
+            assert_eq!(
+                compensated
+                    .render_diff_image(expected.as_ref())
+                    .unwrap()
+                    .width(),
+                12
+            );
+        }
+    }
+
+    /// Translation search must not pick a tiny overlap, and images too small to compare must
+    /// not cause a panic.
+    #[test]
+    fn translation_with_small_images() {
+        let expected = crate::image::from_fn(10, 10, |x, y| {
+            luma_to_rgba(u8::try_from((x * 37 + y * 91 + x * y * 13) % 200).unwrap())
+        });
+        let mut actual = expected.clone();
+        actual[(0usize, 0usize)] = expected[(9usize, 9usize)];
+        actual[(5usize, 5usize)] = [255, 0, 0, 255];
+        let mut differ = Differ::new()
+            .search_translation(20)
+            .compensate_translation(true);
+        let difference = differ.compare(actual.as_ref(), expected.as_ref());
+        assert_eq!(difference.translation(), Some([0, 0]));
+        assert_eq!(difference.compared_area(), Some(Rect::new(1, 1, 8, 8)));
+
+        for [width, height] in [[1, 1], [2, 5], [5, 2]] {
+            let tiny = crate::image::from_fn(width, height, |_, _| luma_to_rgba(0));
+            let difference = differ.compare(tiny.as_ref(), tiny.as_ref());
+            assert_eq!(difference.compared_area(), None);
+            assert_eq!(difference.histogram().max_difference(), 255);
+        }
+    }
+
+    #[test]
+    fn full_size_diff_image() {
+        let pattern =
+            |x: usize, y: usize| luma_to_rgba(u8::try_from((x * 5 + y * 11) % 50).unwrap());
+        let expected = crate::image::from_fn(16, 12, pattern);
+        let actual = crate::image::from_fn(16, 12, |x, y| {
+            if (x, y) == (6, 7) {
+                [255, 255, 255, 255]
+            } else {
+                pattern(x + 2, y + 49)
+            }
+        });
+
+        let mut differ = Differ::new()
+            .search_translation(3)
+            .compensate_translation(true);
+        let cropped = differ.compare(actual.as_ref(), expected.as_ref());
+        let mut differ = differ.full_size_diff_image(true);
+        let full = differ.compare(actual.as_ref(), expected.as_ref());
+
+        assert_eq!(full.diff_image_area(), Some(Rect::new(0, 0, 16, 12)));
+        let full_image = full.diff_image().unwrap();
+        assert_eq!((full_image.width(), full_image.height()), (16, 12));
+        let area = cropped.diff_image_area().unwrap();
+        let cropped_image = cropped.diff_image().unwrap();
+        for y in 0..12 {
+            for x in 0..16 {
+                let pixel = full_image[(x, y)];
+                if area.contains(x, y) {
+                    assert_eq!(pixel, cropped_image[(x - area.x, y - area.y)]);
+                } else {
+                    assert_eq!(pixel[1..], [0, 128, 255], "({x}, {y})");
+                }
+            }
+        }
+
+        let sparse = differ
+            .sparse(true)
+            .compare(actual.as_ref(), expected.as_ref());
+        assert_eq!(
+            sparse.render_diff_image(expected.as_ref()).as_ref(),
+            full.diff_image.as_ref()
+        );
+    }
+
+    #[test]
+    fn edges_ignore_fill_but_not_geometry() {
+        let square = |offset: usize, fill: u8| {
+            crate::image::from_fn(12, 12, move |x, y| {
+                let inside = (3 + offset..7 + offset).contains(&x) && (3..7).contains(&y);
+                luma_to_rgba(if inside { fill } else { 0 })
+            })
+        };
+        let expected = square(0, 200);
+        let mut differ = Differ::new().compare_edges(true);
+
+        // Same shape, different fill; only the edge contrast changed, slightly.
+        let recolored = square(0, 190);
+        let difference = differ.compare(recolored.as_ref(), expected.as_ref());
+        assert!(difference.histogram().max_difference() <= 10);
+
+        // Moved shape.
+        let moved = square(3, 200);
+        let mut difference = differ.compare(moved.as_ref(), expected.as_ref());
+        assert!(difference.histogram().max_difference() > 100);
+
+        differ.update(
+            &mut difference,
+            recolored.as_ref(),
+            expected.as_ref(),
+            Rect::new(0, 0, 12, 12),
+        );
+        assert_eq!(
+            difference,
+            differ.compare(recolored.as_ref(), expected.as_ref())
+        );
+    }
+
+    #[test]
+    fn full_size_diff_image_update() {
+        let expected = crate::image::from_fn(10, 10, |_, _| luma_to_rgba(0));
+        let mut actual = expected.clone();
+        let mut differ = Differ::new().full_size_diff_image(true);
+        let mut difference = differ.compare(actual.as_ref(), expected.as_ref());
+
+        actual[(4usize, 5usize)] = luma_to_rgba(200);
+        differ.update(
+            &mut difference,
+            actual.as_ref(),
+            expected.as_ref(),
+            Rect::new(4, 5, 1, 1),
+        );
+        assert_eq!(
+            difference,
+            differ.compare(actual.as_ref(), expected.as_ref())
+        );
+        assert_eq!(difference.diff_image().unwrap()[(4usize, 5usize)][1], 255);
+    }
+
+    #[test]
+    fn update_with_changed_size() {
+        let image1 = crate::image::from_fn(10, 10, |_, _| [1, 2, 3, 255]);
+        let image2 = crate::image::from_fn(9, 9, |_, _| [100, 200, 255, 255]);
+        let mut differ = Differ::new();
+        let mut difference = differ.compare(image1.as_ref(), image1.as_ref());
+        differ.update(
+            &mut difference,
+            image2.as_ref(),
+            image1.as_ref(),
+            Rect::new(0, 0, 1, 1),
         );
+        assert_eq!(difference, diff(image2.as_ref(), image1.as_ref()));
     }
 
     /// Verify that the neighborhood comparison covers the expected neighborhood
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use imgref::ImgRef;
+
+use crate::diff::{dimensions, interior, pixel_diff};
+use crate::{Direction, Rect, RgbaPixel};
+
+/// Output of [`displacement_map()`]; the location of the best match for each pixel.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DisplacementMap {
+    /// The region of the input images for which offsets were computed.
+    area: Rect,
+    /// Offsets for each pixel in `area` in row-major order, indexed by [`Direction`] as in
+    /// [`Difference`](crate::Difference).
+    offsets: [Vec<Option<[i8; 2]>>; 2],
+}
+
+/// Finds, for each pixel of each image, the nearest pixel of the other image which
+/// matches it, and records the offset to that pixel.
+///
+/// Whereas [`diff()`](crate::diff()) only considers the best match within a 1-pixel
+/// neighborhood, this searches squares of increasing radius, up to `max_radius`, until it
+/// finds a pixel whose difference magnitude is no greater than `tolerance`.
+/// If there are several such pixels at the same radius, the one with the smallest difference
+/// is chosen, and then the first in row-major order.
+///
+/// This allows distinguishing, for example, a rendering change which shifts everything by
+/// one pixel consistently from one which jitters edges in varying directions.
+///
+/// As with [`diff()`](crate::diff()), the pixels at the edges of the images are not
+/// themselves searched for, though they may be found as matches.
+/// If the images have different sizes, then no pixels are searched for.
+///
+/// # Panics
+///
+/// Panics if `max_radius` is greater than 127.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::{imgref::ImgVec, Direction};
+///
+/// let mut expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+/// let mut actual = expected.clone();
+/// // A dot which should be at (4, 4) but is 2 pixels to the right.
+/// expected[(4usize, 4usize)] = [255, 255, 255, 255];
+/// actual[(6usize, 4usize)] = [255, 255, 255, 255];
+///
+/// let map = rendiff::displacement_map(actual.as_ref(), expected.as_ref(), 3, 0);
+///
+/// assert_eq!(map.offset(Direction::Missing, 4, 4), Some([2, 0]));
+/// assert_eq!(map.offset(Direction::Extra, 6, 4), Some([-2, 0]));
+/// assert_eq!(map.radius_counts(Direction::Extra)[2], 1);
+/// ```
+#[must_use]
+pub fn displacement_map(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    max_radius: usize,
+    tolerance: u8,
+) -> DisplacementMap {
+    assert!(
+        max_radius <= 127,
+        "max_radius {max_radius} is greater than the maximum of 127"
+    );
+    let area = if dimensions(actual) == dimensions(expected) {
+        interior(expected)
+    } else {
+        Rect::default()
+    };
+    let search = |have: ImgRef<'_, RgbaPixel>, want: ImgRef<'_, RgbaPixel>| {
+        let mut offsets = Vec::with_capacity(area.area());
+        for y in area.y..area.y_end() {
+            for x in area.x..area.x_end() {
+                offsets.push(find_match(have[(x, y)], want, x, y, max_radius, tolerance));
+            }
+        }
+        offsets
+    };
+    DisplacementMap {
+        area,
+        offsets: [search(expected, actual), search(actual, expected)],
+    }
+}
+
+impl DisplacementMap {
+    /// Returns the offset from the pixel at `(x, y)` in one image to its match in the
+    /// other image, or [`None`] if no match was found or the pixel was not searched for.
+    ///
+    /// If `direction` is [`Direction::Missing`], the pixel is from the `expected` image and
+    /// the match is in the `actual` image; if [`Direction::Extra`], the reverse.
+    #[must_use]
+    pub fn offset(&self, direction: Direction, x: usize, y: usize) -> Option<[isize; 2]> {
+        if !self.area.contains(x, y) {
+            return None;
+        }
+        self.offsets[direction.index()][(y - self.area.y) * self.area.width + (x - self.area.x)]
+            .map(|offset| offset.map(isize::from))
+    }
+
+    /// Returns the number of pixels which matched at each offset.
+    /// Pixels which found no match are not included.
+    ///
+    /// An image which was uniformly shifted will have a single large count at that offset,
+    /// whereas edges which moved inconsistently will have counts spread over many offsets.
+    #[must_use]
+    pub fn offset_counts(&self, direction: Direction) -> BTreeMap<[isize; 2], usize> {
+        let mut counts = BTreeMap::new();
+        for offset in self.offsets[direction.index()].iter().flatten() {
+            *counts.entry(offset.map(isize::from)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of pixels which matched at each distance, measured as the
+    /// larger of the horizontal and vertical offsets.
+    ///
+    /// Index 0 of the result is the count of pixels which matched in place, index 1 is
+    /// those which matched in the 1-pixel neighborhood that [`diff()`](crate::diff())
+    /// examines, and so on.
+    /// Pixels which found no match are not included.
+    #[must_use]
+    pub fn radius_counts(&self, direction: Direction) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for [dx, dy] in self.offsets[direction.index()].iter().flatten() {
+            let radius = usize::from(dx.unsigned_abs().max(dy.unsigned_abs()));
+            if counts.len() <= radius {
+                counts.resize(radius + 1, 0);
+            }
+            counts[radius] += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of pixels which were searched for but found no match.
+    #[must_use]
+    pub fn unmatched_count(&self, direction: Direction) -> usize {
+        self.offsets[direction.index()]
+            .iter()
+            .filter(|offset| offset.is_none())
+            .count()
+    }
+}
+
+/// Searches `want` in squares of increasing radius around `(x, y)` for a pixel matching
+/// `pixel`, and returns the offset to it.
+pub(crate) fn find_match(
+    pixel: RgbaPixel,
+    want: ImgRef<'_, RgbaPixel>,
+    x: usize,
+    y: usize,
+    max_radius: usize,
+    tolerance: u8,
+) -> Option<[i8; 2]> {
+    let bounds = Rect::new(0, 0, want.width(), want.height());
+    for radius in 0..=max_radius {
+        let square = Rect::new(x, y, 1, 1).expand(radius).intersection(bounds);
+        let mut best: Option<(u8, [usize; 2])> = None;
+        for wy in square.y..square.y_end() {
+            for wx in square.x..square.x_end() {
+                // Only examine the ring at this radius; the interior was already searched.
+                if wx.abs_diff(x).max(wy.abs_diff(y)) != radius {
+                    continue;
+                }
+                let magnitude = pixel_diff(pixel, want[(wx, wy)]);
+                if magnitude <= tolerance && best.map_or(true, |(b, _)| magnitude < b) {
+                    best = Some((magnitude, [wx, wy]));
+                }
+            }
+        }
+        if let Some((_, [wx, wy])) = best {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+            // radius is at most 127
+            return Some([
+                (wx as isize - x as isize) as i8,
+                (wy as isize - y as isize) as i8,
+            ]);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn uniform_shift() {
+        let expected = crate::image::from_fn(12, 12, |x, y| {
+            luma_to_rgba(u8::try_from(x * 20 + y).unwrap())
+        });
+        // Shifted right by 2, so each pixel of actual matches the one 2 to the left
+        // in expected, except near the left edge.
+        let actual = crate::image::from_fn(12, 12, |x, y| expected[(x.saturating_sub(2), y)]);
+        let map = displacement_map(actual.as_ref(), expected.as_ref(), 3, 0);
+
+        let counts = map.offset_counts(Direction::Extra);
+        assert_eq!(counts[&[-2, 0]], 90);
+        assert_eq!(map.radius_counts(Direction::Extra), vec![0, 10, 90]);
+        assert_eq!(map.unmatched_count(Direction::Extra), 0);
+        assert_eq!(map.offset(Direction::Extra, 5, 5), Some([-2, 0]));
+        assert_eq!(map.offset(Direction::Missing, 5, 5), Some([2, 0]));
+        // The rightmost compared column of expected was shifted out of actual.
+        assert_eq!(map.unmatched_count(Direction::Missing), 10);
+        // Edges are not searched for.
+        assert_eq!(map.offset(Direction::Extra, 0, 5), None);
+    }
+
+    #[test]
+    fn tolerance_and_unmatched() {
+        let expected = crate::image::from_fn(5, 5, |_, _| luma_to_rgba(0));
+        let actual =
+            crate::image::from_fn(5, 5, |x, y| luma_to_rgba(u8::from((x, y) == (2, 2)) * 10));
+
+        let strict = displacement_map(actual.as_ref(), expected.as_ref(), 1, 0);
+        assert_eq!(strict.offset(Direction::Extra, 2, 2), None);
+        assert_eq!(strict.unmatched_count(Direction::Extra), 1);
+        // Expected pixels all find a match nearby, because actual is mostly black.
+        assert_eq!(strict.offset(Direction::Missing, 2, 2), Some([-1, -1]));
+        assert_eq!(strict.unmatched_count(Direction::Missing), 0);
+
+        let tolerant = displacement_map(actual.as_ref(), expected.as_ref(), 1, 10);
+        assert_eq!(tolerant.offset(Direction::Extra, 2, 2), Some([0, 0]));
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let [actual, expected] = crate::image::mismatched_images();
+        let map = displacement_map(actual.as_ref(), expected.as_ref(), 1, 0);
+        assert_eq!(map.offset(Direction::Extra, 2, 2), None);
+        assert!(map.offset_counts(Direction::Extra).is_empty());
+        assert_eq!(map.unmatched_count(Direction::Missing), 0);
+    }
+}
@@ -0,0 +1,73 @@
+use imgref::{ImgRef, ImgVec};
+
+use crate::image::{luma_to_rgba, rgba_to_luma};
+use crate::RgbaPixel;
+
+/// Computes the magnitude of the [Sobel] gradient of the luma and of the alpha of each pixel
+/// of `image`, as a grayscale image of the same size, writing the result into `buffer`.
+///
+/// Each output pixel is the larger of the two gradient magnitudes, so that edges of
+/// transparency are found as well as edges of color.
+/// Pixels beyond the edges of the image are taken to be copies of the nearest edge pixel.
+/// The magnitude is scaled so that a sharp edge between black and white, or between
+/// transparent and opaque, has the maximum value.
+///
+/// [Sobel]: https://en.wikipedia.org/wiki/Sobel_operator
+pub(crate) fn edge_map(
+    image: ImgRef<'_, RgbaPixel>,
+    mut buffer: Vec<RgbaPixel>,
+) -> ImgVec<RgbaPixel> {
+    let (width, height) = (image.width(), image.height());
+    let neighbor = |x: usize, y: usize, dx: isize, dy: isize| -> RgbaPixel {
+        let x = x.saturating_add_signed(dx).min(width - 1);
+        let y = y.saturating_add_signed(dy).min(height - 1);
+        image[(x, y)]
+    };
+
+    buffer.clear();
+    for y in 0..height {
+        for x in 0..width {
+            let magnitude = |channel: fn(RgbaPixel) -> u8| -> u8 {
+                let v = |dx, dy| i32::from(channel(neighbor(x, y, dx, dy)));
+                let gradient_x =
+                    (v(1, -1) + 2 * v(1, 0) + v(1, 1)) - (v(-1, -1) + 2 * v(-1, 0) + v(-1, 1));
+                let gradient_y =
+                    (v(-1, 1) + 2 * v(0, 1) + v(1, 1)) - (v(-1, -1) + 2 * v(0, -1) + v(1, -1));
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let magnitude = (f64::from(gradient_x).hypot(f64::from(gradient_y)) / 4.0)
+                    .round()
+                    .min(255.0) as u8;
+                magnitude
+            };
+            buffer.push(luma_to_rgba(
+                magnitude(rgba_to_luma).max(magnitude(|pixel| pixel[3])),
+            ));
+        }
+    }
+    ImgVec::new(buffer, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertical_edge() {
+        let image = crate::image::from_fn(4, 3, |x, _| luma_to_rgba(u8::from(x >= 2) * 255));
+        let edges = edge_map(image.as_ref(), Vec::new());
+        assert_eq!(
+            edges.pixels().map(|p| p[0]).collect::<Vec<_>>(),
+            vec![0, 255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0]
+        );
+    }
+
+    #[test]
+    fn alpha_edge() {
+        let image = crate::image::from_fn(4, 3, |x, _| [100, 100, 100, u8::from(x >= 2) * 255]);
+        let edges = edge_map(image.as_ref(), Vec::new());
+        assert_eq!(
+            edges.pixels().map(|p| p[0]).collect::<Vec<_>>(),
+            vec![0, 255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0]
+        );
+    }
+}
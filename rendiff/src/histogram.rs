@@ -1,4 +1,4 @@
-use core::fmt;
+use core::{fmt, iter, ops};
 
 #[cfg(doc)]
 use crate::{Difference, Threshold};
@@ -49,6 +49,356 @@ impl Histogram {
             None => 0,
         }
     }
+
+    /// Returns the total number of pixels counted in the histogram, including those with
+    /// zero difference.
+    #[must_use]
+    pub fn total_count(&self) -> usize {
+        self.0.iter().sum()
+    }
+
+    /// Returns the number of pixels with a difference greater than zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut histogram = rendiff::Histogram::ZERO;
+    /// histogram.0[0] = 90;
+    /// histogram.0[3] = 8;
+    /// histogram.0[40] = 2;
+    ///
+    /// assert_eq!(histogram.total_count(), 100);
+    /// assert_eq!(histogram.differing_count(), 10);
+    /// assert_eq!(histogram.differing_fraction(), 0.1);
+    /// ```
+    #[must_use]
+    pub fn differing_count(&self) -> usize {
+        self.0[1..].iter().sum()
+    }
+
+    /// Returns the fraction, from 0 to 1, of the pixels which have a difference greater than
+    /// zero.
+    ///
+    /// Returns zero if the histogram is entirely empty.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // a ratio of pixel counts needs only relative precision
+    pub fn differing_fraction(&self) -> f64 {
+        let total = self.total_count();
+        if total == 0 {
+            0.0
+        } else {
+            self.differing_count() as f64 / total as f64
+        }
+    }
+
+    /// Returns the mean difference magnitude of all pixels, including those with zero
+    /// difference.
+    ///
+    /// Returns zero if the histogram is entirely empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut histogram = rendiff::Histogram::ZERO;
+    /// histogram.0[0] = 2;
+    /// histogram.0[4] = 2;
+    ///
+    /// assert_eq!(histogram.mean(), 2.0);
+    /// assert_eq!(histogram.rms(), 8.0f64.sqrt());
+    /// ```
+    #[must_use]
+    pub fn mean(&self) -> f64 {
+        self.weighted_mean(|magnitude| magnitude)
+    }
+
+    /// Returns the root-mean-square difference magnitude of all pixels, including those with
+    /// zero difference.
+    ///
+    /// This gives more weight to large differences than [`Histogram::mean()`] does.
+    /// Returns zero if the histogram is entirely empty.
+    #[must_use]
+    pub fn rms(&self) -> f64 {
+        self.weighted_mean(|magnitude| magnitude * magnitude).sqrt()
+    }
+
+    /// Returns the smallest magnitude such that at least `percent` percent of the pixels have
+    /// a difference no greater than it (the “nearest-rank” definition of percentile).
+    ///
+    /// For example, `percentile(50.0)` is the median and `percentile(100.0)` is the same as
+    /// [`Histogram::max_difference()`].
+    /// Returns zero if the histogram is entirely empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percent` is not between 0 and 100 inclusive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut histogram = rendiff::Histogram::ZERO;
+    /// histogram.0[0] = 950;
+    /// histogram.0[2] = 45;
+    /// histogram.0[200] = 5;
+    ///
+    /// assert_eq!(histogram.percentile(50.0), 0);
+    /// assert_eq!(histogram.percentile(99.0), 2);
+    /// assert_eq!(histogram.percentile(99.9), 200);
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn percentile(&self, percent: f64) -> u8 {
+        assert!(
+            (0.0..=100.0).contains(&percent),
+            "percentile {percent} is not between 0 and 100"
+        );
+        let total = self.total_count();
+        // Number of pixels which must be at or below the result, at least 1.
+        let rank = ((percent / 100.0 * total as f64).ceil() as usize).max(1);
+        let mut cumulative = 0;
+        for (magnitude, &count) in self.0.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= rank {
+                return magnitude as u8;
+            }
+        }
+        0
+    }
+
+    /// Returns the change in each count from `baseline` to `self`.
+    ///
+    /// This is useful for tracking how the aggregate differences of a test suite change
+    /// from one run to another.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::Histogram;
+    ///
+    /// let mut baseline = Histogram::ZERO;
+    /// baseline.0[0] = 100;
+    /// baseline.0[2] = 10;
+    /// let mut current = baseline;
+    /// current.0[0] -= 3;
+    /// current.0[7] += 3;
+    ///
+    /// let delta = current.delta_from(&baseline);
+    /// assert_eq!(delta.count(7), 3);
+    /// assert_eq!(format!("{delta:?}"), "HistogramDelta(Δ0 -3, Δ7 +3)");
+    /// assert!(!current.is_no_worse_than(&baseline));
+    /// assert!(baseline.is_no_worse_than(&current));
+    /// ```
+    #[must_use]
+    pub fn delta_from(&self, baseline: &Histogram) -> HistogramDelta {
+        HistogramDelta(core::array::from_fn(|i| {
+            // Two's complement subtraction gives the correct result for any change which
+            // fits in `isize`.
+            #[allow(clippy::cast_possible_wrap)]
+            let change = self.0[i].wrapping_sub(baseline.0[i]) as isize;
+            change
+        }))
+    }
+
+    /// Returns whether, for every magnitude, this histogram has no more pixels with a
+    /// difference of that magnitude or greater than `baseline` does.
+    ///
+    /// That is, no difference got worse, even if the total number of pixels differs.
+    /// Pixels with zero difference are not counted, so adding identical pixels never
+    /// counts as worse.
+    #[must_use]
+    pub fn is_no_worse_than(&self, baseline: &Histogram) -> bool {
+        let mut count_at_or_above = 0usize;
+        let mut baseline_count_at_or_above = 0usize;
+        for (&count, &baseline_count) in self.0[1..].iter().zip(&baseline.0[1..]).rev() {
+            count_at_or_above += count;
+            baseline_count_at_or_above += baseline_count;
+            if count_at_or_above > baseline_count_at_or_above {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns a one-line summary of the distribution of differences, with one character
+    /// for each of the ranges of magnitudes shown by the [`Display`](fmt::Display) chart,
+    /// whose height is log-scaled like the bars of that chart.
+    ///
+    /// Ranges which contain no differences are shown as a space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut histogram = rendiff::Histogram::ZERO;
+    /// histogram.0[0] = 1000;
+    /// histogram.0[5] = 10;
+    /// histogram.0[200] = 1;
+    ///
+    /// assert_eq!(histogram.sparkline(), "█  ▃    ▁");
+    /// ```
+    #[must_use]
+    pub fn sparkline(&self) -> String {
+        const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let counts = self.bucket_counts();
+        let max_count = counts.iter().copied().max().unwrap_or(0);
+        counts
+            .iter()
+            .map(|&count| match log_scale(count, max_count, LEVELS.len()) {
+                0 => ' ',
+                level => LEVELS[level - 1],
+            })
+            .collect::<String>()
+            .trim_end()
+            .to_owned()
+    }
+
+    /// Returns the total counts of the magnitude ranges given by [`bucket_range()`].
+    fn bucket_counts(&self) -> [usize; BUCKETS] {
+        core::array::from_fn(|i| {
+            let range = bucket_range(i);
+            self.0[usize::from(*range.start())..=usize::from(*range.end())]
+                .iter()
+                .sum()
+        })
+    }
+
+    /// Returns the mean of `f(magnitude)` over all pixels, or zero if there are none.
+    #[allow(clippy::cast_precision_loss)] // pixel counts are exact in f64 up to 2^53
+    fn weighted_mean(&self, f: impl Fn(f64) -> f64) -> f64 {
+        let total = self.total_count();
+        if total == 0 {
+            return 0.0;
+        }
+        let sum: f64 = (0u8..=255)
+            .zip(&self.0)
+            .map(|(magnitude, &count)| f(f64::from(magnitude)) * count as f64)
+            .sum();
+        sum / total as f64
+    }
+}
+
+/// Prints a chart of the distribution of differences, with one line for each of a series of
+/// ranges of magnitudes, each twice as large as the last.
+///
+/// The length of each bar is proportional to the logarithm of the count, so that a few large
+/// differences are still visible next to many small ones.
+///
+/// # Example
+///
+/// ```
+/// let mut histogram = rendiff::Histogram::ZERO;
+/// histogram.0[0] = 1000;
+/// histogram.0[5] = 10;
+/// histogram.0[200] = 1;
+///
+/// println!("{histogram}");
+/// assert_eq!(
+///     histogram.to_string().lines().nth(3),
+///     Some("    4–7 │██████████████ 10"),
+/// );
+/// ```
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const WIDTH: usize = 40;
+        let counts = self.bucket_counts();
+        let max_count = counts.iter().copied().max().unwrap_or(0);
+        for (i, &count) in counts.iter().enumerate() {
+            let range = bucket_range(i);
+            let label = if range.start() == range.end() {
+                range.start().to_string()
+            } else {
+                format!("{}–{}", range.start(), range.end())
+            };
+            let bar = "█".repeat(log_scale(count, max_count, WIDTH));
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{label:>7} │{bar} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of ranges of magnitudes used by [`Histogram`]’s chart and sparkline.
+const BUCKETS: usize = 9;
+
+/// Returns the range of magnitudes in bucket `i` of [`Histogram`]’s chart and sparkline:
+/// 0, then 1, 2–3, 4–7, and so on up to 128–255.
+fn bucket_range(i: usize) -> core::ops::RangeInclusive<u8> {
+    if i == 0 {
+        return 0..=0;
+    }
+    let start = 1u16 << (i - 1);
+    let end = (start << 1) - 1;
+    #[allow(clippy::cast_possible_truncation)] // i < BUCKETS
+    {
+        (start as u8)..=(end as u8)
+    }
+}
+
+/// Scales `count` to an integer from 0 to `length` such that the result is proportional to
+/// the logarithm of the count, `max_count` is scaled to `length`, and only zero is scaled
+/// to zero.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn log_scale(count: usize, max_count: usize, length: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    let fraction = (count as f64).ln_1p() / (max_count as f64).ln_1p();
+    ((fraction * length as f64).round() as usize).clamp(1, length)
+}
+
+/// Adds the counts of each magnitude, such as to aggregate the results of several
+/// comparisons.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::Histogram;
+///
+/// let frames = [Histogram([1; 256]), Histogram([2; 256]), Histogram::ZERO];
+/// assert_eq!(frames.iter().sum::<Histogram>(), Histogram([3; 256]));
+/// assert_eq!(frames[0] + frames[1], Histogram([3; 256]));
+/// ```
+impl ops::Add for Histogram {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+impl ops::AddAssign for Histogram {
+    fn add_assign(&mut self, rhs: Self) {
+        for (count, rhs_count) in self.0.iter_mut().zip(rhs.0) {
+            *count += rhs_count;
+        }
+    }
+}
+
+impl iter::Sum for Histogram {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, ops::Add::add)
+    }
+}
+
+impl<'a> iter::Sum<&'a Histogram> for Histogram {
+    fn sum<I: Iterator<Item = &'a Histogram>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+/// Sums the histograms, like [`Sum`](iter::Sum).
+impl FromIterator<Histogram> for Histogram {
+    fn from_iter<I: IntoIterator<Item = Histogram>>(iter: I) -> Self {
+        iter.into_iter().sum()
+    }
 }
 
 impl fmt::Debug for Histogram {
@@ -76,6 +426,49 @@ impl fmt::Debug for Histogram {
     }
 }
 
+/// The change in each count of a [`Histogram`] relative to another,
+/// produced by [`Histogram::delta_from()`].
+///
+/// Its [`Debug`](fmt::Debug) implementation lists only the magnitudes whose counts changed.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct HistogramDelta([isize; 256]);
+
+impl HistogramDelta {
+    /// Returns the change in the count of differences of the given magnitude.
+    #[must_use]
+    pub fn count(&self, magnitude: u8) -> isize {
+        self.0[usize::from(magnitude)]
+    }
+
+    /// Returns whether no count changed.
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|&change| change == 0)
+    }
+
+    /// Returns the change in the number of pixels with a difference greater than zero.
+    #[must_use]
+    pub fn differing_count(&self) -> isize {
+        self.0[1..].iter().sum()
+    }
+}
+
+impl fmt::Debug for HistogramDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HistogramDelta(")?;
+        let mut first = true;
+        for (delta, change) in self.0.iter().enumerate().filter(|&(_, &c)| c != 0) {
+            if first {
+                first = false;
+            } else {
+                write!(f, ", ")?;
+            }
+            write!(f, "Δ{delta} {change:+}")?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,4 +512,87 @@ mod tests {
             50,
         );
     }
+
+    #[test]
+    fn statistics_of_empty() {
+        let zero = Histogram::ZERO;
+        assert_eq!(zero.total_count(), 0);
+        assert_eq!(zero.differing_count(), 0);
+        assert!(zero.differing_fraction() == 0.0);
+        assert!(zero.mean() == 0.0);
+        assert!(zero.rms() == 0.0);
+        assert_eq!(zero.percentile(0.0), 0);
+        assert_eq!(zero.percentile(100.0), 0);
+    }
+
+    #[test]
+    fn percentile() {
+        let mut h = Histogram::ZERO;
+        h.0[1] = 1;
+        h.0[5] = 2;
+        h.0[9] = 1;
+        assert_eq!(h.percentile(0.0), 1);
+        assert_eq!(h.percentile(25.0), 1);
+        assert_eq!(h.percentile(25.1), 5);
+        assert_eq!(h.percentile(75.0), 5);
+        assert_eq!(h.percentile(75.1), 9);
+        assert_eq!(h.percentile(100.0), h.max_difference());
+    }
+
+    #[test]
+    fn chart() {
+        let mut h = Histogram::ZERO;
+        h.0[0] = 1000;
+        h.0[3] = 1;
+        h.0[5] = 10;
+        h.0[6] = 20;
+        h.0[200] = 1;
+        assert_eq!(
+            h.to_string(),
+            "      0 │████████████████████████████████████████ 1000\n\
+             \x20     1 │ 0\n\
+             \x20   2–3 │████ 1\n\
+             \x20   4–7 │████████████████████ 30\n\
+             \x20  8–15 │ 0\n\
+             \x20 16–31 │ 0\n\
+             \x20 32–63 │ 0\n\
+             \x2064–127 │ 0\n\
+             128–255 │████ 1"
+        );
+        assert_eq!(h.sparkline(), "█ ▁▄    ▁");
+        assert_eq!(Histogram::ZERO.sparkline(), "");
+    }
+
+    #[test]
+    fn sum_and_delta() {
+        let mut a = Histogram::ZERO;
+        a.0[0] = 10;
+        a.0[5] = 1;
+        let mut b = Histogram::ZERO;
+        b.0[0] = 4;
+        b.0[200] = 2;
+
+        let total: Histogram = [a, b].into_iter().collect();
+        assert_eq!(total, a + b);
+        assert_eq!(format!("{total:#?}"), "Histogram(Δ0 ×14, Δ5 ×1, Δ200 ×2)");
+
+        let delta = b.delta_from(&a);
+        assert_eq!(
+            format!("{delta:?}"),
+            "HistogramDelta(Δ0 -6, Δ5 -1, Δ200 +2)"
+        );
+        assert_eq!(delta.differing_count(), 1);
+        assert!(!delta.is_zero());
+        assert!(a.delta_from(&a).is_zero());
+
+        assert!(a.is_no_worse_than(&total));
+        assert!(!total.is_no_worse_than(&b));
+        assert!(Histogram::ZERO.is_no_worse_than(&Histogram::ZERO));
+    }
+
+    #[test]
+    #[should_panic = "percentile 101 is not between 0 and 100"]
+    fn percentile_out_of_range() {
+        let _ = Histogram::ZERO.percentile(101.0);
+    }
 }
@@ -39,7 +39,46 @@ pub(crate) fn rgba_to_luma(pixel: RgbaPixel) -> u8 {
     }
 }
 
-#[cfg(test)]
+/// Halves the size of the image, rounding up, by averaging each 2×2 block of pixels.
+///
+/// Each channel is averaged independently, which is not colorimetrically correct,
+/// but is adequate for finding where images differ.
+pub(crate) fn downsample(image: imgref::ImgRef<'_, RgbaPixel>) -> imgref::ImgVec<RgbaPixel> {
+    let width = image.width().div_ceil(2);
+    let height = image.height().div_ceil(2);
+    from_fn(width, height, |x, y| {
+        let mut sum = [0u32; 4];
+        let mut count = 0u32;
+        for source_y in (y * 2)..(y * 2 + 2).min(image.height()) {
+            for source_x in (x * 2)..(x * 2 + 2).min(image.width()) {
+                let pixel = image[(source_x, source_y)];
+                for (s, c) in sum.iter_mut().zip(pixel) {
+                    *s += u32::from(c);
+                }
+                count += 1;
+            }
+        }
+        sum.map(|s| {
+            let average = (s + count / 2) / count;
+            debug_assert!(u8::try_from(average).is_ok());
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                average as u8
+            }
+        })
+    })
+}
+
 pub(crate) fn luma_to_rgba(luma: u8) -> RgbaPixel {
     [luma, luma, luma, 255]
 }
+
+/// Returns two uniform images of slightly different sizes, `[actual, expected]`, for testing
+/// how mismatched sizes are handled.
+#[cfg(test)]
+pub(crate) fn mismatched_images() -> [imgref::ImgVec<RgbaPixel>; 2] {
+    [
+        from_fn(5, 5, |_, _| luma_to_rgba(0)),
+        from_fn(5, 6, |_, _| luma_to_rgba(0)),
+    ]
+}
@@ -153,13 +153,59 @@ type RgbaPixel = [u8; 4];
 
 mod image;
 
+mod classify;
+pub use classify::*;
+
+mod color_correction;
+pub use color_correction::*;
+
+mod colors;
+pub use colors::*;
+
 mod diff;
 pub use diff::*;
 
+mod displacement;
+pub use displacement::*;
+
+mod edges;
+
 mod histogram;
 pub use histogram::*;
 
+mod locate;
+pub use locate::*;
+
+mod probe;
+pub use probe::*;
+
+mod profiles;
+pub use profiles::*;
+
+mod pyramid;
+pub use pyramid::*;
+
+mod quality;
+pub use quality::*;
+
+mod rect;
+pub use rect::*;
+
+mod regions;
+pub use regions::*;
+
+mod ssim;
+pub use ssim::*;
+
+mod stream;
+pub use stream::*;
+
 mod threshold;
 pub use threshold::*;
 
+mod translation;
+
 mod visualize;
+
+mod worst;
+pub use worst::*;
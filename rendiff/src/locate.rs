@@ -0,0 +1,165 @@
+use imgref::ImgRef;
+
+use crate::translation::total_difference_up_to;
+use crate::{Differ, Difference, Rect, RgbaPixel};
+
+/// Output of [`Differ::locate()`]; where the `expected` image was found within the
+/// `actual` image, and how well it matched.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Location {
+    /// The position in the `actual` image of the top left corner of the best placement of
+    /// the `expected` image.
+    pub offset: [usize; 2],
+
+    /// Whether the `expected` image was found exactly, with every pixel identical.
+    pub exact: bool,
+
+    /// Whether more than one placement matched equally well.
+    /// If so, [`Location::offset`] is the first such placement in row-major order.
+    pub ambiguous: bool,
+
+    /// The comparison of the `expected` image with the part of the `actual` image at
+    /// [`Location::offset`].
+    /// Its coordinates are those of the `expected` image.
+    pub difference: Difference,
+}
+
+impl Differ {
+    /// Finds the placement of the `expected` image within the larger `actual` image where
+    /// it matches best, and compares it there.
+    ///
+    /// This is useful when, for example, `actual` is a screenshot of an entire window and
+    /// `expected` is an image of only one part of it.
+    ///
+    /// First, every placement is checked for an exact match.
+    /// If there are none, then the placement with the smallest total difference magnitude
+    /// is chosen.
+    /// In either case, if more than one placement is equally good, the result is marked
+    /// [ambiguous](Location::ambiguous).
+    /// The search takes time proportional to the size of `actual` times the size of
+    /// `expected` (much less when there is an exact match and few near-matches).
+    ///
+    /// Returns [`None`] if `expected` is larger than `actual` in either dimension.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// let window = ImgVec::new(
+    ///     (0..10_000).map(|i| [(i % 251) as u8, (i / 100) as u8, 0, 255]).collect(),
+    ///     100,
+    ///     100,
+    /// );
+    /// let widget = ImgVec::new(window.sub_image(40, 25, 10, 8).pixels().collect(), 10, 8);
+    ///
+    /// let location = Differ::new().locate(window.as_ref(), widget.as_ref()).unwrap();
+    /// assert_eq!(location.offset, [40, 25]);
+    /// assert!(location.exact);
+    /// assert!(!location.ambiguous);
+    /// assert_eq!(location.difference.histogram().max_difference(), 0);
+    /// ```
+    #[must_use]
+    pub fn locate(
+        &mut self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+    ) -> Option<Location> {
+        let x_range = actual.width().checked_sub(expected.width())? + 1;
+        let y_range = actual.height().checked_sub(expected.height())? + 1;
+        let placement = |x: usize, y: usize| {
+            Rect::new(x, y, expected.width(), expected.height()).sub_image(actual)
+        };
+
+        let placements = (0..y_range).flat_map(|y| (0..x_range).map(move |x| [x, y]));
+
+        // First, look for placements where every pixel is identical.
+        let mut exact_matches = placements.clone().filter(|&[x, y]| {
+            placement(x, y)
+                .rows()
+                .zip(expected.rows())
+                .all(|(actual_row, expected_row)| actual_row == expected_row)
+        });
+        let (offset @ [x, y], exact, ambiguous) = if let Some(offset) = exact_matches.next() {
+            (offset, true, exact_matches.next().is_some())
+        } else {
+            // Best (total difference, offset, whether ambiguous) found so far.
+            let mut best: Option<(u64, [usize; 2], bool)> = None;
+            for [x, y] in placements {
+                // Placements which are worse than the best so far need not be fully examined.
+                let limit = best.map_or(u64::MAX, |(total, _, _)| total);
+                let Some(total) = total_difference_up_to(placement(x, y), expected, limit) else {
+                    continue;
+                };
+                match best {
+                    Some((best_total, _, ref mut ambiguous)) if total == best_total => {
+                        *ambiguous = true;
+                    }
+                    _ => best = Some((total, [x, y], false)),
+                }
+            }
+            let (_, offset, ambiguous) = best?;
+            (offset, false, ambiguous)
+        };
+
+        Some(Location {
+            offset,
+            exact,
+            ambiguous,
+            difference: self.compare(placement(x, y), expected),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn tolerant_match() {
+        let actual = crate::image::from_fn(30, 20, |x, y| {
+            luma_to_rgba(u8::try_from((x * 3 + y * 17) % 200).unwrap())
+        });
+        let mut expected = crate::image::from_fn(6, 5, |x, y| actual[(x + 11, y + 9)]);
+        expected[(2usize, 2usize)] = [255, 0, 0, 255];
+
+        let location = Differ::new()
+            .locate(actual.as_ref(), expected.as_ref())
+            .unwrap();
+        assert_eq!(location.offset, [11, 9]);
+        assert!(!location.exact);
+        assert!(!location.ambiguous);
+        assert_eq!(
+            location
+                .difference
+                .differing_pixels()
+                .map(|d| (d.x, d.y))
+                .collect::<Vec<_>>(),
+            vec![(2, 2)]
+        );
+    }
+
+    #[test]
+    fn ambiguous() {
+        let actual = crate::image::from_fn(10, 10, |x, _| luma_to_rgba(u8::from(x % 4 == 0) * 255));
+        let expected = crate::image::from_fn(3, 3, |x, _| luma_to_rgba(u8::from(x == 1) * 255));
+        let location = Differ::new()
+            .locate(actual.as_ref(), expected.as_ref())
+            .unwrap();
+        assert_eq!(location.offset, [3, 0]);
+        assert!(location.exact);
+        assert!(location.ambiguous);
+    }
+
+    #[test]
+    fn too_large() {
+        let actual = crate::image::from_fn(10, 10, |_, _| luma_to_rgba(0));
+        let expected = crate::image::from_fn(11, 3, |_, _| luma_to_rgba(0));
+        assert_eq!(
+            Differ::new().locate(actual.as_ref(), expected.as_ref()),
+            None
+        );
+    }
+}
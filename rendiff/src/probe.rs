@@ -0,0 +1,231 @@
+use core::fmt;
+
+use imgref::ImgRef;
+
+use crate::diff::{dimensions, pixel_diff};
+use crate::worst::Hex;
+use crate::{Direction, Rect, RgbaPixel};
+
+/// Explanation of how [`diff()`](crate::diff()) computed the difference at a single pixel,
+/// produced by [`probe()`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Probe {
+    /// X coordinate of the pixel in the input images.
+    pub x: usize,
+    /// Y coordinate of the pixel in the input images.
+    pub y: usize,
+    /// Value of the pixel in the `actual` image.
+    pub actual: RgbaPixel,
+    /// Value of the pixel in the `expected` image.
+    pub expected: RgbaPixel,
+    /// The final difference magnitude, which is the larger of the magnitudes of the two
+    /// directions of comparison.
+    pub magnitude: u8,
+
+    /// Indexed by [`Direction::index()`].
+    halves: [HalfProbe; 2],
+}
+
+/// Explanation of one direction of the comparison at a single pixel; part of a [`Probe`].
+///
+/// In this direction, one image's pixel (the `have` pixel) is compared with each pixel of a
+/// 3×3 neighborhood of the other image, and the smallest difference is the result.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct HalfProbe {
+    /// The pixel which was compared with the neighborhood.
+    /// This is from the `expected` image for [`Direction::Missing`],
+    /// and from the `actual` image for [`Direction::Extra`].
+    pub have: RgbaPixel,
+    /// The neighborhood of the other image, indexed as `[dy][dx]` where `[1][1]` is the
+    /// center.
+    pub neighborhood: [[RgbaPixel; 3]; 3],
+    /// The difference between [`HalfProbe::have`] and each pixel of
+    /// [`HalfProbe::neighborhood`], indexed the same way.
+    pub differences: [[u8; 3]; 3],
+    /// The `[dx, dy]` index into [`HalfProbe::neighborhood`] of the first (in row-major order)
+    /// pixel with the smallest difference.
+    pub best: [usize; 2],
+    /// The smallest difference, which is the result of this direction of comparison.
+    pub magnitude: u8,
+}
+
+/// Explains how [`diff()`](crate::diff()) computed the difference magnitude at the pixel
+/// `(x, y)` of the input images.
+///
+/// This is intended for interactive debugging of the comparison algorithm, or of surprising
+/// results from it.
+/// It does not take into account any of the options of [`Differ`](crate::Differ), such as
+/// translation compensation, color correction, or edge comparison, so its results do not
+/// explain comparisons made with those options;
+/// [`Difference::worst_pixels()`](crate::Difference::worst_pixels) does take them into
+/// account.
+///
+/// Returns [`None`] if the images have different sizes, or if `(x, y)` is not a pixel which
+/// is compared (which excludes the edges of the images).
+///
+/// # Example
+///
+/// ```
+/// use rendiff::{imgref::ImgVec, Direction};
+///
+/// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 5 * 5], 5, 5);
+/// let mut actual = expected.clone();
+/// actual[(2usize, 2usize)] = [100, 100, 100, 255];
+///
+/// let probe = rendiff::probe(actual.as_ref(), expected.as_ref(), 2, 2).unwrap();
+/// assert_eq!(probe.magnitude, 100);
+/// assert_eq!(probe.half(Direction::Extra).magnitude, 100);
+/// assert_eq!(probe.half(Direction::Missing).magnitude, 0);
+/// // The black expected pixel matched the first black neighbor in actual.
+/// assert_eq!(probe.half(Direction::Missing).best, [0, 0]);
+/// // The Display implementation prints all of the above as a table.
+/// assert!(probe
+///     .to_string()
+///     .starts_with("(2, 2): expected #000000FF, actual #646464FF, magnitude 100\n"));
+/// ```
+#[must_use]
+pub fn probe(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    x: usize,
+    y: usize,
+) -> Option<Probe> {
+    // Pixels on the edges are not compared, so the images need not be big enough to have
+    // an interior.
+    let [width, height] = dimensions(expected);
+    if dimensions(actual) != [width, height]
+        || !(1..width.saturating_sub(1)).contains(&x)
+        || !(1..height.saturating_sub(1)).contains(&y)
+    {
+        return None;
+    }
+    let halves = [(expected, actual), (actual, expected)].map(|(have, want)| {
+        let have = have[(x, y)];
+        let neighborhood_image = Rect::new(x - 1, y - 1, 3, 3).sub_image(want);
+        let neighborhood: [[RgbaPixel; 3]; 3] =
+            core::array::from_fn(|dy| core::array::from_fn(|dx| neighborhood_image[(dx, dy)]));
+        let differences = neighborhood.map(|row| row.map(|want| pixel_diff(have, want)));
+        let mut best = [0, 0];
+        for (dy, row) in differences.iter().enumerate() {
+            for (dx, &difference) in row.iter().enumerate() {
+                if difference < differences[best[1]][best[0]] {
+                    best = [dx, dy];
+                }
+            }
+        }
+        HalfProbe {
+            have,
+            neighborhood,
+            differences,
+            best,
+            magnitude: differences[best[1]][best[0]],
+        }
+    });
+    Some(Probe {
+        x,
+        y,
+        actual: actual[(x, y)],
+        expected: expected[(x, y)],
+        magnitude: halves[0].magnitude.max(halves[1].magnitude),
+        halves,
+    })
+}
+
+impl Probe {
+    /// Returns the explanation of one direction of the comparison.
+    #[must_use]
+    pub fn half(&self, direction: Direction) -> &HalfProbe {
+        &self.halves[direction.index()]
+    }
+}
+
+/// Prints a multi-line table of the neighborhoods and differences.
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "({}, {}): expected {}, actual {}, magnitude {}",
+            self.x,
+            self.y,
+            Hex(self.expected),
+            Hex(self.actual),
+            self.magnitude
+        )?;
+        for direction in [Direction::Missing, Direction::Extra] {
+            let half = self.half(direction);
+            writeln!(
+                f,
+                "{direction:?}: {} compared with neighborhood, magnitude {}",
+                Hex(half.have),
+                half.magnitude
+            )?;
+            for (dy, (pixels, differences)) in
+                half.neighborhood.iter().zip(&half.differences).enumerate()
+            {
+                for (dx, (pixel, difference)) in pixels.iter().zip(differences).enumerate() {
+                    let marker = if [dx, dy] == half.best { '*' } else { ' ' };
+                    write!(f, "  {} {difference:>3}{marker}", Hex(*pixel))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    /// The probe must agree with the actual comparison at every pixel.
+    #[test]
+    fn probe_matches_diff() {
+        let expected = crate::image::from_fn(7, 6, |x, y| luma_to_rgba(u8::from(x > y) * 100));
+        let actual = crate::image::from_fn(7, 6, |x, y| {
+            luma_to_rgba(u8::from(x > y + 1) * 100 + u8::from((x, y) == (2, 4)) * 30)
+        });
+        let difference = crate::Differ::new()
+            .sparse(true)
+            .compare(actual.as_ref(), expected.as_ref());
+        let differing: Vec<_> = difference.differing_pixels().collect();
+        assert!(!differing.is_empty());
+
+        for y in 0..6 {
+            for x in 0..7 {
+                let Some(probe) = probe(actual.as_ref(), expected.as_ref(), x, y) else {
+                    assert!(x == 0 || y == 0 || x == 6 || y == 5);
+                    continue;
+                };
+                let d = differing.iter().find(|d| (d.x, d.y) == (x, y));
+                assert_eq!(probe.magnitude, d.map_or(0, |d| d.magnitude));
+                for direction in [Direction::Missing, Direction::Extra] {
+                    assert_eq!(
+                        probe.half(direction).magnitude,
+                        d.map_or(0, |d| d.directional_magnitude(direction))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let [actual, expected] = crate::image::mismatched_images();
+        assert_eq!(probe(actual.as_ref(), expected.as_ref(), 2, 2), None);
+    }
+
+    #[test]
+    fn tiny_images() {
+        for [width, height] in [[1, 1], [1, 5], [5, 1], [2, 2]] {
+            let image = crate::image::from_fn(width, height, |_, _| luma_to_rgba(0));
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(probe(image.as_ref(), image.as_ref(), x, y), None);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::Difference;
+
+/// Aggregate of the differences in one row or column of pixels, produced by
+/// [`Difference::row_profiles()`] and [`Difference::column_profiles()`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct LineProfile {
+    /// The number of pixels in the row or column with a nonzero difference magnitude.
+    pub differing_pixels: usize,
+    /// The largest difference magnitude of any pixel in the row or column.
+    pub max_magnitude: u8,
+}
+
+/// Whether lines of pixels are rows or columns.
+#[derive(Clone, Copy)]
+enum Axis {
+    Rows,
+    Columns,
+}
+
+impl Difference {
+    /// Returns the aggregate differences of each row of pixels, indexed by the y coordinate
+    /// of the input images.
+    ///
+    /// This is useful for finding bugs which affect whole scanlines.
+    /// Rows which were not compared, such as the edges of the images, have zero differences.
+    /// If the images had different sizes, the result is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::imgref::ImgVec;
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+    /// let mut actual = expected.clone();
+    /// actual[(3usize, 6usize)] = [40, 40, 40, 255];
+    /// actual[(7usize, 6usize)] = [90, 90, 90, 255];
+    ///
+    /// let rows = rendiff::diff(actual.as_ref(), expected.as_ref()).row_profiles();
+    /// assert_eq!(rows.len(), 10);
+    /// assert_eq!(rows[6].differing_pixels, 2);
+    /// assert_eq!(rows[6].max_magnitude, 90);
+    /// assert_eq!(rows[5].differing_pixels, 0);
+    /// ```
+    #[must_use]
+    pub fn row_profiles(&self) -> Vec<LineProfile> {
+        self.profiles(Axis::Rows)
+    }
+
+    /// Returns the aggregate differences of each column of pixels, indexed by the x
+    /// coordinate of the input images.
+    ///
+    /// See [`Difference::row_profiles()`] for details.
+    #[must_use]
+    pub fn column_profiles(&self) -> Vec<LineProfile> {
+        self.profiles(Axis::Columns)
+    }
+
+    /// Returns the y coordinates of the rows in which every compared pixel has a difference
+    /// magnitude greater than `level`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::imgref::ImgVec;
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 10 * 10], 10, 10);
+    /// let mut actual = expected.clone();
+    /// for x in 0..10usize {
+    ///     actual[(x, 4usize)] = [255, 0, 0, 255];
+    /// }
+    ///
+    /// let difference = rendiff::diff(actual.as_ref(), expected.as_ref());
+    /// assert_eq!(difference.fully_different_rows(0), vec![4]);
+    /// assert_eq!(difference.fully_different_columns(0), vec![]);
+    /// ```
+    #[must_use]
+    pub fn fully_different_rows(&self, level: u8) -> Vec<usize> {
+        self.fully_different_lines(Axis::Rows, level)
+    }
+
+    /// Returns the x coordinates of the columns in which every compared pixel has a
+    /// difference magnitude greater than `level`.
+    ///
+    /// See [`Difference::fully_different_rows()`] for details.
+    #[must_use]
+    pub fn fully_different_columns(&self, level: u8) -> Vec<usize> {
+        self.fully_different_lines(Axis::Columns, level)
+    }
+
+    fn profiles(&self, axis: Axis) -> Vec<LineProfile> {
+        let Some(size) = self.input_size() else {
+            return Vec::new();
+        };
+        let mut profiles = vec![LineProfile::default(); axis.length(size)];
+        for d in self.differing_pixels() {
+            let profile = &mut profiles[axis.coordinate(d.x, d.y)];
+            profile.differing_pixels += 1;
+            profile.max_magnitude = profile.max_magnitude.max(d.magnitude);
+        }
+        profiles
+    }
+
+    fn fully_different_lines(&self, axis: Axis, level: u8) -> Vec<usize> {
+        let Some(area) = self.compared_area() else {
+            return Vec::new();
+        };
+        let Some(size) = self.input_size() else {
+            return Vec::new();
+        };
+        let mut counts = vec![0; axis.length(size)];
+        for d in self.differing_pixels().filter(|d| d.magnitude > level) {
+            counts[axis.coordinate(d.x, d.y)] += 1;
+        }
+        let line_length = match axis {
+            Axis::Rows => area.width,
+            Axis::Columns => area.height,
+        };
+        counts
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count == line_length && line_length > 0)
+            .map(|(coordinate, _)| coordinate)
+            .collect()
+    }
+}
+
+impl Axis {
+    /// Returns the coordinate along this axis which identifies the line a pixel is in.
+    fn coordinate(self, x: usize, y: usize) -> usize {
+        match self {
+            Axis::Rows => y,
+            Axis::Columns => x,
+        }
+    }
+
+    /// Returns the number of lines in an image of the given size.
+    fn length(self, [width, height]: [usize; 2]) -> usize {
+        match self {
+            Axis::Rows => height,
+            Axis::Columns => width,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn column_profiles_and_fully_different_columns() {
+        let expected = crate::image::from_fn(8, 6, |_, _| luma_to_rgba(0));
+        let actual = crate::image::from_fn(8, 6, |x, y| {
+            luma_to_rgba(match (x, y) {
+                (2, _) => 100,
+                (5, 1..=3) => 50,
+                _ => 0,
+            })
+        });
+        let difference = crate::diff(actual.as_ref(), expected.as_ref());
+
+        let columns = difference.column_profiles();
+        assert_eq!(columns.len(), 8);
+        assert_eq!(
+            columns
+                .iter()
+                .map(|p| (p.differing_pixels, p.max_magnitude))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 0),
+                (0, 0),
+                (4, 100),
+                (0, 0),
+                (0, 0),
+                (3, 50),
+                (0, 0),
+                (0, 0)
+            ]
+        );
+        assert_eq!(difference.fully_different_columns(0), vec![2]);
+        assert_eq!(difference.fully_different_columns(100), vec![]);
+        assert_eq!(difference.fully_different_rows(0), vec![]);
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let [actual, expected] = crate::image::mismatched_images();
+        let difference = crate::diff(actual.as_ref(), expected.as_ref());
+        assert_eq!(difference.row_profiles(), vec![]);
+        assert_eq!(difference.fully_different_rows(0), vec![]);
+    }
+}
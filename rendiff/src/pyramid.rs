@@ -0,0 +1,338 @@
+use core::mem;
+
+use imgref::{ImgRef, ImgVec};
+
+use crate::diff::{diff_region, dimensions, interior};
+use crate::{Differ, Histogram, Rect, RgbaPixel};
+
+#[cfg(doc)]
+use crate::{diff, Threshold};
+
+/// Size, in pixels of the level being compared, of the square tiles which are either
+/// compared or skipped as a unit.
+const TILE_SIZE: usize = 16;
+
+/// Output of [`Differ::compare_pyramid()`]; a comparison between two images at several scales.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct PyramidDifference {
+    /// Results for each level, finest (full resolution) first.
+    levels: Vec<Level>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Level {
+    histogram: Histogram,
+    compared_pixels: usize,
+}
+
+impl PyramidDifference {
+    /// Returns the number of levels of the pyramid which were compared.
+    ///
+    /// This may be fewer than requested, if the images were too small to be downsampled
+    /// further.
+    #[must_use]
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns the histogram of differences found at the given level of the pyramid.
+    ///
+    /// Level 0 is the full-resolution images, level 1 is the images downsampled to half size,
+    /// level 2 is quarter size, and so on.
+    /// Pixels which were not compared, because the coarser level indicated no differences
+    /// nearby, are counted as having no difference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not less than [`PyramidDifference::level_count()`].
+    #[must_use]
+    pub fn histogram(&self, level: usize) -> Histogram {
+        self.levels[level].histogram
+    }
+
+    /// Returns the number of pixels at the given level of the pyramid which were compared,
+    /// rather than skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not less than [`PyramidDifference::level_count()`].
+    #[must_use]
+    pub fn compared_pixels(&self, level: usize) -> usize {
+        self.levels[level].compared_pixels
+    }
+}
+
+impl Differ {
+    /// Compares two RGBA images at multiple scales, examining finer scales only where
+    /// coarser scales found differences.
+    ///
+    /// Both images are repeatedly downsampled to half size, producing “pyramids” of up to
+    /// `levels` images each, and the smallest images are compared as by [`diff()`].
+    /// Then, each larger level is divided into tiles, and each tile is compared only if
+    /// the corresponding region of the previous, coarser level contained any nonzero
+    /// differences or was at the edge of the image.
+    ///
+    /// Because downsampling averages pixels, this can miss differences which average
+    /// out, such as a checkerboard pattern being shifted by one pixel.
+    /// Nonetheless, it is very fast when the images are identical or differ only in a small
+    /// region, and the per-level histograms allow you to set different [`Threshold`]s for
+    /// different scales, such as permitting small displacements at full resolution while
+    /// requiring the coarser levels to match closely.
+    ///
+    /// None of the [`Differ`]'s options apply to this comparison; each level is compared
+    /// pixel by pixel, as by [`diff()`], but using the [`Differ`]'s retained memory.
+    ///
+    /// If `levels` is zero, it is treated as 1.
+    /// If the images have different sizes, or are too small to have any pixels to compare
+    /// (smaller than 3×3), then the result will have a single level which is the maximum
+    /// difference, as [`diff()`] would report.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ, Threshold};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 256 * 256], 256, 256);
+    /// let mut actual = expected.clone();
+    /// actual[(100usize, 100usize)] = [255, 255, 255, 255];
+    ///
+    /// let pyramid = Differ::new().compare_pyramid(actual.as_ref(), expected.as_ref(), 4);
+    ///
+    /// assert_eq!(pyramid.level_count(), 4);
+    /// // One pixel has a large difference at full resolution…
+    /// assert!(!Threshold::no_bigger_than(254).allows(pyramid.histogram(0)));
+    /// // …but it is mostly averaged away at lower resolutions.
+    /// assert!(Threshold::no_bigger_than(64).allows(pyramid.histogram(1)));
+    /// // Only the areas around that pixel, and the edges of the image, were examined at
+    /// // full resolution.
+    /// assert!(pyramid.compared_pixels(0) < 254 * 254 / 2);
+    /// ```
+    #[must_use]
+    pub fn compare_pyramid(
+        &mut self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        levels: usize,
+    ) -> PyramidDifference {
+        let [width, height] = dimensions(expected);
+        if dimensions(actual) != [width, height] || width < 3 || height < 3 {
+            return PyramidDifference {
+                levels: vec![Level {
+                    histogram: self.mismatched_sizes(actual, expected).histogram(),
+                    compared_pixels: 0,
+                }],
+            };
+        }
+
+        // Build the pyramids, stopping when further levels would have no pixels to compare.
+        let mut downsampled: Vec<[ImgVec<RgbaPixel>; 2]> = Vec::new();
+        while downsampled.len() + 1 < levels {
+            let [last_actual, last_expected] = match downsampled.last() {
+                Some([a, e]) => [a.as_ref(), e.as_ref()],
+                None => [actual, expected],
+            };
+            if last_actual.width() < 6 || last_actual.height() < 6 {
+                break;
+            }
+            downsampled.push([
+                crate::image::downsample(last_actual),
+                crate::image::downsample(last_expected),
+            ]);
+        }
+        let pyramid: Vec<[ImgRef<'_, RgbaPixel>; 2]> = core::iter::once([actual, expected])
+            .chain(downsampled.iter().map(|[a, e]| [a.as_ref(), e.as_ref()]))
+            .collect();
+
+        let mut results = Vec::with_capacity(pyramid.len());
+        // Difference values of the previous (coarser) level, with zero where not compared.
+        let mut coarser: Option<ImgVec<u8>> = None;
+        let mut buffers = mem::take(&mut self.region_buffers);
+        let mut spare_magnitudes = mem::take(&mut self.spare_magnitude_buffers);
+        for &[level_actual, level_expected] in pyramid.iter().rev() {
+            let (width, height) = (level_expected.width(), level_expected.height());
+            let interior = interior(level_expected);
+
+            let mut magnitudes = mem::take(&mut spare_magnitudes[0]);
+            magnitudes.clear();
+            magnitudes.resize(width * height, 0);
+            let mut magnitudes = ImgVec::new(magnitudes, width, height);
+            let mut compared_pixels = 0;
+            for tile_y in (0..height).step_by(TILE_SIZE) {
+                for tile_x in (0..width).step_by(TILE_SIZE) {
+                    let tile =
+                        Rect::new(tile_x, tile_y, TILE_SIZE, TILE_SIZE).intersection(interior);
+                    if tile.is_empty()
+                        || !coarser.as_ref().map_or(true, |coarser| {
+                            coarse_region_differs(coarser.as_ref(), tile)
+                        })
+                    {
+                        continue;
+                    }
+
+                    for buffer in &mut buffers {
+                        buffer.clear();
+                    }
+                    diff_region(level_actual, level_expected, tile, &mut buffers);
+                    compared_pixels += tile.area();
+                    let mut values = buffers[0].iter();
+                    for y in tile.y..tile.y_end() {
+                        for (x, &value) in (tile.x..tile.x_end()).zip(&mut values) {
+                            magnitudes[(x, y)] = value;
+                        }
+                    }
+                }
+            }
+
+            let mut histogram = Histogram::ZERO;
+            for y in interior.y..interior.y_end() {
+                for x in interior.x..interior.x_end() {
+                    histogram.0[usize::from(magnitudes[(x, y)])] += 1;
+                }
+            }
+            results.push(Level {
+                histogram,
+                compared_pixels,
+            });
+            if let Some(previous) = coarser.replace(magnitudes) {
+                spare_magnitudes[0] = previous.into_buf();
+            }
+        }
+        if let Some(last) = coarser {
+            spare_magnitudes[1] = last.into_buf();
+        }
+        self.region_buffers = buffers;
+        self.spare_magnitude_buffers = spare_magnitudes;
+
+        results.reverse();
+        PyramidDifference { levels: results }
+    }
+}
+
+/// Returns whether the part of `coarser` corresponding to `tile` of the next finer level,
+/// or its neighborhood, either has nonzero differences or was not compared because it is
+/// at the edge.
+fn coarse_region_differs(coarser: ImgRef<'_, u8>, tile: Rect) -> bool {
+    let coarse_tile = Rect::new(
+        tile.x / 2,
+        tile.y / 2,
+        tile.x_end().div_ceil(2) - tile.x / 2,
+        tile.y_end().div_ceil(2) - tile.y / 2,
+    )
+    .expand(1);
+    let coarse_interior = interior(coarser);
+    if coarse_tile.intersection(coarse_interior) != coarse_tile {
+        return true;
+    }
+    coarse_tile
+        .sub_image(coarser)
+        .pixels()
+        .any(|value| value != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn identical_images_skip_everything_but_edges() {
+        let image = crate::image::from_fn(100, 100, |x, y| {
+            luma_to_rgba(u8::from((x / 7 + y / 3) % 2 == 0) * 200)
+        });
+        let pyramid = Differ::new().compare_pyramid(image.as_ref(), image.as_ref(), 3);
+        assert_eq!(pyramid.level_count(), 3);
+        for level in 0..3 {
+            assert_eq!(pyramid.histogram(level).max_difference(), 0);
+        }
+        // The coarsest level is compared entirely.
+        assert_eq!(pyramid.compared_pixels(2), 23 * 23);
+        // Finer levels only compare the tiles near the edges.
+        assert!(pyramid.compared_pixels(0) < 98 * 98);
+    }
+
+    #[test]
+    fn levels_match_diff_of_downsampled_where_compared() {
+        let expected = crate::image::from_fn(40, 30, |x, y| luma_to_rgba(u8::from(x > y) * 100));
+        let actual = crate::image::from_fn(40, 30, |x, y| {
+            luma_to_rgba(u8::from(x > y) * 100 + u8::from((20..24).contains(&x) && y < 4) * 50)
+        });
+        let pyramid = Differ::new().compare_pyramid(actual.as_ref(), expected.as_ref(), 2);
+
+        assert_eq!(pyramid.level_count(), 2);
+        assert_eq!(
+            pyramid.histogram(1),
+            crate::diff(
+                crate::image::downsample(actual.as_ref()).as_ref(),
+                crate::image::downsample(expected.as_ref()).as_ref()
+            )
+            .histogram()
+        );
+        // The difference is large enough to be noticed at the coarse level,
+        // so the full-resolution result is exact.
+        assert_eq!(
+            pyramid.histogram(0),
+            crate::diff(actual.as_ref(), expected.as_ref()).histogram()
+        );
+    }
+
+    /// Reusing a [`Differ`], with options, for pyramid and ordinary comparisons does not
+    /// affect the results.
+    #[test]
+    fn reused_differ() {
+        let expected = crate::image::from_fn(40, 30, |x, y| luma_to_rgba(u8::from(x > y) * 100));
+        let actual = crate::image::from_fn(40, 30, |x, y| {
+            luma_to_rgba(u8::from(x > y) * 100 + u8::from(x == 20 && y < 4) * 50)
+        });
+        let fresh = Differ::new().compare_pyramid(actual.as_ref(), expected.as_ref(), 3);
+
+        let mut differ = Differ::new().sparse(true);
+        let difference = differ.compare(expected.as_ref(), actual.as_ref());
+        differ.recycle(difference);
+        assert_eq!(
+            differ.compare_pyramid(actual.as_ref(), expected.as_ref(), 3),
+            fresh
+        );
+        assert_eq!(
+            differ.compare_pyramid(actual.as_ref(), expected.as_ref(), 3),
+            fresh
+        );
+        assert_eq!(
+            differ
+                .compare(actual.as_ref(), expected.as_ref())
+                .histogram(),
+            crate::diff(actual.as_ref(), expected.as_ref()).histogram()
+        );
+    }
+
+    #[test]
+    fn too_small_for_requested_levels() {
+        let image = crate::image::from_fn(12, 12, |_, _| luma_to_rgba(0));
+        let pyramid = Differ::new().compare_pyramid(image.as_ref(), image.as_ref(), 10);
+        // 12×12, 6×6, and 3×3, which has only one pixel to compare.
+        assert_eq!(pyramid.level_count(), 3);
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let [actual, expected] = crate::image::mismatched_images();
+        let pyramid = Differ::new().compare_pyramid(actual.as_ref(), expected.as_ref(), 3);
+        assert_eq!(pyramid.level_count(), 1);
+        assert_eq!(pyramid.histogram(0).max_difference(), 255);
+    }
+
+    #[test]
+    fn too_small_to_compare() {
+        for [width, height] in [[2, 2], [2, 10], [10, 1]] {
+            let black = crate::image::from_fn(width, height, |_, _| luma_to_rgba(0));
+            let white = crate::image::from_fn(width, height, |_, _| luma_to_rgba(255));
+            let pyramid = Differ::new().compare_pyramid(black.as_ref(), white.as_ref(), 3);
+            assert_eq!(pyramid.level_count(), 1);
+            assert_eq!(
+                pyramid.histogram(0),
+                crate::diff(black.as_ref(), white.as_ref()).histogram()
+            );
+            assert_eq!(pyramid.histogram(0).max_difference(), 255);
+        }
+    }
+}
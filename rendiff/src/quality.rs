@@ -0,0 +1,205 @@
+use imgref::ImgRef;
+
+use crate::{Histogram, RgbaPixel};
+
+#[cfg(doc)]
+use crate::{Differ, Difference};
+
+/// Conventional image quality metrics, computed if [`Differ::quality_metrics()`] is enabled
+/// and reported by [`Difference::quality_metrics()`].
+///
+/// These are computed in two ways:
+///
+/// * Over the raw channel values of corresponding pixels, which is the conventional
+///   definition, and agrees with other image processing tools.
+///   This uses every pixel of the compared images, including the edges (but only the parts
+///   which overlap, if [translation was compensated](Differ::compensate_translation)),
+///   after [color correction](Differ::correct_color) if any.
+/// * Over the neighborhood-tolerant difference magnitudes which make up the
+///   [histogram](Difference::histogram), which is not comparable with other tools, but
+///   agrees with the rest of the results of this library.
+///   If [edges are compared](Differ::compare_edges), these are the differences between the
+///   edge maps, not the original images.
+///
+/// All values are on the 0–255 scale of the images.
+/// PSNR is in decibels, and is infinite if there is no error.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct QualityMetrics {
+    /// Sum of squared differences of each channel, in RGBA order.
+    channel_squared_error: [u64; 4],
+    /// Number of pixels whose channels were summed.
+    pixel_count: usize,
+    /// Sum of squared difference magnitudes.
+    magnitude_squared_error: u64,
+    /// Number of magnitudes which were summed.
+    magnitude_count: usize,
+}
+
+impl QualityMetrics {
+    /// Computes the metrics for the given images, which must be the same size, and the
+    /// histogram of their difference magnitudes.
+    pub(crate) fn compute(
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        histogram: &Histogram,
+    ) -> Self {
+        let mut channel_squared_error = [0u64; 4];
+        for (actual_pixel, expected_pixel) in actual.pixels().zip(expected.pixels()) {
+            for ((sum, a), e) in channel_squared_error
+                .iter_mut()
+                .zip(actual_pixel)
+                .zip(expected_pixel)
+            {
+                let error = u64::from(a.abs_diff(e));
+                *sum += error * error;
+            }
+        }
+        Self {
+            channel_squared_error,
+            pixel_count: expected.width() * expected.height(),
+            magnitude_squared_error: (0u64..)
+                .zip(&histogram.0)
+                .map(|(magnitude, &count)| magnitude * magnitude * count as u64)
+                .sum(),
+            magnitude_count: histogram.total_count(),
+        }
+    }
+
+    /// Returns the mean squared error over all channels of all pixels.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Differ};
+    ///
+    /// let expected = ImgVec::new(vec![[100, 100, 100, 255u8]; 10 * 10], 10, 10);
+    /// let actual = ImgVec::new(vec![[104, 100, 100, 255u8]; 10 * 10], 10, 10);
+    ///
+    /// let difference = Differ::new()
+    ///     .quality_metrics(true)
+    ///     .compare(actual.as_ref(), expected.as_ref());
+    /// let metrics = difference.quality_metrics().unwrap();
+    /// assert_eq!(metrics.channel_mse(), [16.0, 0.0, 0.0, 0.0]);
+    /// assert_eq!(metrics.mse(), 4.0);
+    /// assert_eq!(metrics.rmse(), 2.0);
+    /// assert!((metrics.psnr() - 42.11).abs() < 0.01);
+    /// ```
+    #[must_use]
+    pub fn mse(&self) -> f64 {
+        self.channel_mse().iter().sum::<f64>() / 4.0
+    }
+
+    /// Returns the root mean squared error over all channels of all pixels.
+    #[must_use]
+    pub fn rmse(&self) -> f64 {
+        self.mse().sqrt()
+    }
+
+    /// Returns the peak signal-to-noise ratio over all channels of all pixels.
+    #[must_use]
+    pub fn psnr(&self) -> f64 {
+        psnr(self.mse())
+    }
+
+    /// Returns the mean squared error of each channel, in RGBA order.
+    #[must_use]
+    pub fn channel_mse(&self) -> [f64; 4] {
+        self.channel_squared_error
+            .map(|sum| mean(sum, self.pixel_count))
+    }
+
+    /// Returns the root mean squared error of each channel, in RGBA order.
+    #[must_use]
+    pub fn channel_rmse(&self) -> [f64; 4] {
+        self.channel_mse().map(f64::sqrt)
+    }
+
+    /// Returns the peak signal-to-noise ratio of each channel, in RGBA order.
+    #[must_use]
+    pub fn channel_psnr(&self) -> [f64; 4] {
+        self.channel_mse().map(psnr)
+    }
+
+    /// Returns the mean squared difference magnitude.
+    #[must_use]
+    pub fn magnitude_mse(&self) -> f64 {
+        mean(self.magnitude_squared_error, self.magnitude_count)
+    }
+
+    /// Returns the root mean squared difference magnitude.
+    /// This is equal to [`Histogram::rms()`] of the same comparison.
+    #[must_use]
+    pub fn magnitude_rmse(&self) -> f64 {
+        self.magnitude_mse().sqrt()
+    }
+
+    /// Returns the peak signal-to-noise ratio computed from the difference magnitudes.
+    #[must_use]
+    pub fn magnitude_psnr(&self) -> f64 {
+        psnr(self.magnitude_mse())
+    }
+}
+
+/// Returns `sum / count`, or zero if `count` is zero.
+#[allow(clippy::cast_precision_loss)] // rounding a huge sum of squared errors barely changes the mean
+fn mean(sum: u64, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64
+    }
+}
+
+/// Converts mean squared error to peak signal-to-noise ratio, in decibels.
+fn psnr(mse: f64) -> f64 {
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::luma_to_rgba;
+    use crate::Differ;
+
+    #[test]
+    #[allow(clippy::float_cmp)] // all of these values are exactly representable
+    fn raw_and_magnitude_metrics() {
+        // A 1-pixel shift of a line is a large raw error but no tolerant difference,
+        // and a lone dot is both.
+        let expected = crate::image::from_fn(10, 10, |x, _| luma_to_rgba(u8::from(x == 3) * 50));
+        let actual = crate::image::from_fn(10, 10, |x, y| {
+            luma_to_rgba(u8::from(x == 4) * 50 + u8::from((x, y) == (7, 7)) * 10)
+        });
+
+        let difference = Differ::new()
+            .quality_metrics(true)
+            .compare(actual.as_ref(), expected.as_ref());
+        let metrics = difference.quality_metrics().unwrap();
+
+        // 20 pixels differ by 50 and one by 10 in each of R, G, and B.
+        let channel_mse = (20.0 * 2500.0 + 100.0) / 100.0;
+        assert_eq!(
+            metrics.channel_mse(),
+            [channel_mse, channel_mse, channel_mse, 0.0]
+        );
+        assert_eq!(metrics.mse(), channel_mse * 3.0 / 4.0);
+        assert_eq!(metrics.magnitude_mse(), 100.0 / 64.0);
+        assert_eq!(metrics.magnitude_rmse(), difference.histogram().rms());
+        assert_eq!(metrics.channel_psnr()[3], f64::INFINITY);
+    }
+
+    #[test]
+    fn disabled_or_mismatched() {
+        let [actual, expected] = crate::image::mismatched_images();
+        assert_eq!(
+            crate::diff(actual.as_ref(), actual.as_ref()).quality_metrics(),
+            None
+        );
+        assert_eq!(
+            Differ::new()
+                .quality_metrics(true)
+                .compare(actual.as_ref(), expected.as_ref())
+                .quality_metrics(),
+            None
+        );
+    }
+}
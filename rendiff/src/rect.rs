@@ -0,0 +1,147 @@
+/// A rectangle of pixel positions within an image.
+///
+/// The rectangle includes the pixels whose x coordinates are in `x..(x + width)` and whose
+/// y coordinates are in `y..(y + height)`.
+/// Unless otherwise specified, coordinates are those of the input images to [`diff()`].
+///
+/// [`diff()`]: crate::diff()
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Rect {
+    /// X coordinate of the leftmost column of pixels in the rectangle.
+    pub x: usize,
+    /// Y coordinate of the topmost row of pixels in the rectangle.
+    pub y: usize,
+    /// Number of columns of pixels in the rectangle.
+    pub width: usize,
+    /// Number of rows of pixels in the rectangle.
+    pub height: usize,
+}
+
+impl Rect {
+    /// Constructs a [`Rect`] from its position and size.
+    #[must_use]
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns whether this rectangle contains no pixels.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the number of pixels in this rectangle.
+    #[must_use]
+    pub const fn area(self) -> usize {
+        self.width * self.height
+    }
+
+    /// Returns whether the pixel at the given coordinates is within this rectangle.
+    #[must_use]
+    pub const fn contains(self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// Returns the rectangle containing only those pixels which are in both `self` and `other`.
+    ///
+    /// If there are no such pixels, the result is an empty rectangle whose position is
+    /// not specified.
+    #[must_use]
+    pub fn intersection(self, other: Self) -> Self {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let x_end = self.x_end().min(other.x_end());
+        let y_end = self.y_end().min(other.y_end());
+        Self {
+            x,
+            y,
+            width: x_end.saturating_sub(x),
+            height: y_end.saturating_sub(y),
+        }
+    }
+
+    /// Returns this rectangle enlarged by `margin` pixels in every direction,
+    /// except where that would make coordinates negative.
+    pub(crate) fn expand(self, margin: usize) -> Self {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        Self {
+            x,
+            y,
+            width: self.x_end().saturating_add(margin) - x,
+            height: self.y_end().saturating_add(margin) - y,
+        }
+    }
+
+    /// Returns the x coordinate just past the right edge of this rectangle,
+    /// or [`usize::MAX`] if that is not representable.
+    pub(crate) const fn x_end(self) -> usize {
+        self.x.saturating_add(self.width)
+    }
+
+    /// Returns the y coordinate just past the bottom edge of this rectangle,
+    /// or [`usize::MAX`] if that is not representable.
+    pub(crate) const fn y_end(self) -> usize {
+        self.y.saturating_add(self.height)
+    }
+
+    /// Returns the part of `image` within this rectangle.
+    ///
+    /// Panics if the rectangle is not entirely within the image.
+    pub(crate) fn sub_image<T>(self, image: imgref::ImgRef<'_, T>) -> imgref::ImgRef<'_, T> {
+        image.sub_image(self.x, self.y, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection() {
+        assert_eq!(
+            Rect::new(1, 2, 10, 10).intersection(Rect::new(5, 0, 10, 4)),
+            Rect::new(5, 2, 6, 2)
+        );
+        assert!(Rect::new(0, 0, 2, 2)
+            .intersection(Rect::new(2, 0, 2, 2))
+            .is_empty());
+        assert_eq!(
+            Rect::new(usize::MAX - 1, 2, 5, 5).intersection(Rect::new(0, 0, 10, 10)),
+            Rect::new(usize::MAX - 1, 2, 0, 5)
+        );
+        assert_eq!(
+            Rect::new(3, 2, usize::MAX, usize::MAX).intersection(Rect::new(0, 0, 10, 10)),
+            Rect::new(3, 2, 7, 8)
+        );
+    }
+
+    #[test]
+    fn expand_near_limits() {
+        assert_eq!(
+            Rect::new(usize::MAX - 1, 0, 5, 5).expand(1),
+            Rect::new(usize::MAX - 2, 0, 2, 6)
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let rect = Rect::new(1, 2, 3, 4);
+        assert!(rect.contains(1, 2));
+        assert!(rect.contains(3, 5));
+        assert!(!rect.contains(0, 2));
+        assert!(!rect.contains(4, 5));
+        assert!(!rect.contains(3, 6));
+    }
+
+    #[test]
+    fn expand() {
+        assert_eq!(Rect::new(1, 5, 3, 4).expand(2), Rect::new(0, 3, 6, 8));
+    }
+}
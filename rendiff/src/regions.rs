@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use crate::{Difference, PixelDifference, Rect};
+
+/// A connected region of differing pixels, found by [`Difference::regions()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct DifferenceRegion {
+    /// The smallest rectangle containing every pixel of the region,
+    /// in the coordinates of the input images.
+    pub bounding_box: Rect,
+    /// The number of pixels in the region.
+    pub pixel_count: usize,
+    /// The largest difference magnitude of any pixel in the region.
+    pub max_magnitude: u8,
+    /// The mean difference magnitude of the pixels in the region.
+    pub mean_magnitude: f64,
+}
+
+impl Difference {
+    /// Finds the connected regions of pixels whose difference magnitude is greater than
+    /// `level`, and reports their locations and sizes.
+    ///
+    /// Pixels are considered connected if they are adjacent horizontally, vertically,
+    /// or diagonally.
+    /// The regions are sorted by decreasing severity; that is, by decreasing maximum
+    /// magnitude, then decreasing size, then position.
+    ///
+    /// If the images had different sizes, there are no regions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::{imgref::ImgVec, Rect};
+    ///
+    /// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 20 * 20], 20, 20);
+    /// let mut actual = expected.clone();
+    /// // A blob of 4 pixels...
+    /// for (x, y) in [(3usize, 3usize), (4, 3), (4, 4), (5, 5)] {
+    ///     actual[(x, y)] = [100, 100, 100, 255];
+    /// }
+    /// // ...and a single brighter pixel.
+    /// actual[(15usize, 10usize)] = [200, 200, 200, 255];
+    ///
+    /// let regions = rendiff::diff(actual.as_ref(), expected.as_ref()).regions(0);
+    ///
+    /// assert_eq!(regions.len(), 2);
+    /// assert_eq!(regions[0].bounding_box, Rect::new(15, 10, 1, 1));
+    /// assert_eq!(regions[0].max_magnitude, 200);
+    /// assert_eq!(regions[1].bounding_box, Rect::new(3, 3, 3, 3));
+    /// assert_eq!(regions[1].pixel_count, 4);
+    /// ```
+    #[must_use]
+    pub fn regions(&self, level: u8) -> Vec<DifferenceRegion> {
+        let pixels: Vec<PixelDifference> = self
+            .differing_pixels()
+            .filter(|d| d.magnitude > level)
+            .collect();
+        let index_of_position: HashMap<(usize, usize), usize> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, d)| ((d.x, d.y), i))
+            .collect();
+
+        // Union-find forest over indices into `pixels`.
+        let mut parents: Vec<usize> = (0..pixels.len()).collect();
+        for (i, d) in pixels.iter().enumerate() {
+            // Only the neighbors which precede this pixel in row-major order need to be checked,
+            // since the others will check this pixel.
+            let preceding_neighbors = [
+                (d.x.wrapping_sub(1), d.y.wrapping_sub(1)),
+                (d.x, d.y.wrapping_sub(1)),
+                (d.x + 1, d.y.wrapping_sub(1)),
+                (d.x.wrapping_sub(1), d.y),
+            ];
+            for neighbor in preceding_neighbors {
+                if let Some(&j) = index_of_position.get(&neighbor) {
+                    let root_i = find_root(&mut parents, i);
+                    let root_j = find_root(&mut parents, j);
+                    parents[root_i.max(root_j)] = root_i.min(root_j);
+                }
+            }
+        }
+
+        // Accumulate the statistics of each region at its root.
+        let mut region_of_root: HashMap<usize, RegionAccumulator> = HashMap::new();
+        for (i, d) in pixels.iter().enumerate() {
+            let root = find_root(&mut parents, i);
+            region_of_root
+                .entry(root)
+                .or_insert_with(|| RegionAccumulator::new(d))
+                .add(d);
+        }
+
+        let mut regions: Vec<DifferenceRegion> = region_of_root
+            .into_values()
+            .map(RegionAccumulator::finish)
+            .collect();
+        regions.sort_by(|a, b| {
+            b.max_magnitude
+                .cmp(&a.max_magnitude)
+                .then(b.pixel_count.cmp(&a.pixel_count))
+                .then(a.bounding_box.y.cmp(&b.bounding_box.y))
+                .then(a.bounding_box.x.cmp(&b.bounding_box.x))
+        });
+        regions
+    }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        // Path halving
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+struct RegionAccumulator {
+    min: [usize; 2],
+    max: [usize; 2],
+    pixel_count: usize,
+    max_magnitude: u8,
+    magnitude_sum: u64,
+}
+
+impl RegionAccumulator {
+    fn new(first: &PixelDifference) -> Self {
+        Self {
+            min: [first.x, first.y],
+            max: [first.x, first.y],
+            pixel_count: 0,
+            max_magnitude: 0,
+            magnitude_sum: 0,
+        }
+    }
+
+    fn add(&mut self, d: &PixelDifference) {
+        self.min = [self.min[0].min(d.x), self.min[1].min(d.y)];
+        self.max = [self.max[0].max(d.x), self.max[1].max(d.y)];
+        self.pixel_count += 1;
+        self.max_magnitude = self.max_magnitude.max(d.magnitude);
+        self.magnitude_sum += u64::from(d.magnitude);
+    }
+
+    #[allow(clippy::cast_precision_loss)] // a mean of counts needs only relative precision
+    fn finish(self) -> DifferenceRegion {
+        DifferenceRegion {
+            bounding_box: Rect::new(
+                self.min[0],
+                self.min[1],
+                self.max[0] - self.min[0] + 1,
+                self.max[1] - self.min[1] + 1,
+            ),
+            pixel_count: self.pixel_count,
+            max_magnitude: self.max_magnitude,
+            mean_magnitude: self.magnitude_sum as f64 / self.pixel_count as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    /// Makes a pair of images which differ where `pattern` has a nonzero digit,
+    /// by 10 times that digit.
+    fn diff_pattern(pattern: &[&str]) -> Difference {
+        let width = pattern[0].len();
+        let height = pattern.len();
+        let expected = crate::image::from_fn(width, height, |_, _| luma_to_rgba(0));
+        let actual = crate::image::from_fn(width, height, |x, y| {
+            let digit = pattern[y].as_bytes()[x] - b'0';
+            luma_to_rgba(digit * 10)
+        });
+        crate::diff(actual.as_ref(), expected.as_ref())
+    }
+
+    #[test]
+    fn no_regions() {
+        assert_eq!(diff_pattern(&["000", "000", "000"]).regions(0), vec![]);
+    }
+
+    #[test]
+    fn diagonal_and_u_shapes_are_connected() {
+        let difference = diff_pattern(&[
+            "000000000", //
+            "010001010", //
+            "001001010", //
+            "000101110", //
+            "000000000", //
+            "000000000", //
+        ]);
+        let regions = difference.regions(0);
+        assert_eq!(
+            regions
+                .iter()
+                .map(|r| (r.bounding_box, r.pixel_count))
+                .collect::<Vec<_>>(),
+            vec![(Rect::new(5, 1, 3, 3), 7), (Rect::new(1, 1, 3, 3), 3)]
+        );
+    }
+
+    #[test]
+    fn statistics_and_level() {
+        let difference = diff_pattern(&[
+            "000000", //
+            "013000", //
+            "000000", //
+            "000090", //
+            "000000", //
+        ]);
+        assert_eq!(
+            difference.regions(0),
+            vec![
+                DifferenceRegion {
+                    bounding_box: Rect::new(4, 3, 1, 1),
+                    pixel_count: 1,
+                    max_magnitude: 90,
+                    mean_magnitude: 90.0,
+                },
+                DifferenceRegion {
+                    bounding_box: Rect::new(1, 1, 2, 1),
+                    pixel_count: 2,
+                    max_magnitude: 30,
+                    mean_magnitude: 20.0,
+                },
+            ]
+        );
+        assert_eq!(
+            difference
+                .regions(10)
+                .iter()
+                .map(|r| (r.bounding_box, r.pixel_count))
+                .collect::<Vec<_>>(),
+            vec![(Rect::new(4, 3, 1, 1), 1), (Rect::new(2, 1, 1, 1), 1)]
+        );
+    }
+}
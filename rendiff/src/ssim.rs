@@ -0,0 +1,313 @@
+use imgref::{ImgRef, ImgVec};
+
+use crate::diff::{dimensions, histogram_of};
+use crate::image::rgba_to_luma;
+use crate::{Histogram, Rect, RgbaPixel};
+
+/// Radius of the SSIM window; the window is `2 * WINDOW_RADIUS + 1` pixels square.
+const WINDOW_RADIUS: usize = 5;
+const WINDOW_SIZE: usize = WINDOW_RADIUS * 2 + 1;
+/// Standard deviation of the Gaussian weighting of the SSIM window.
+const WINDOW_SIGMA: f64 = 1.5;
+/// Stabilizing constants, for a dynamic range of 255.
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Output of [`ssim()`]; the structural similarity of two images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ssim {
+    /// The region of the input images whose pixels are the centers of windows in `map`.
+    area: Rect,
+    /// SSIM of the window centered on each pixel of `area`.
+    map: ImgVec<f32>,
+    /// Mean of `map`.
+    score: f64,
+    /// Histogram of the dissimilarity magnitudes of `map`.
+    histogram: Histogram,
+}
+
+/// Computes the [structural similarity index measure][SSIM] (SSIM) of two images.
+///
+/// Unlike [`diff()`](crate::diff()), which looks for the closest match for each pixel,
+/// SSIM compares the local mean, variance, and correlation of the images in the window
+/// around each pixel.
+/// It is therefore more suitable for textured or noisy content, where individual pixels
+/// are expected to differ but the overall structure should be preserved.
+///
+/// This uses the conventional parameters: the luma of the pixels is compared using an
+/// 11×11 Gaussian window with σ = 1.5.
+/// Alpha is ignored.
+/// Only windows which lie entirely within the images are used, so a border of 5 pixels is
+/// not at the center of any window.
+///
+/// Returns [`None`] if the images have different sizes, or are smaller than the window.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::imgref::ImgVec;
+///
+/// // A noisy texture, and the same texture with a little more noise.
+/// let expected = ImgVec::new(
+///     (0..64 * 64u32).map(|i| {
+///         let value = (i.wrapping_mul(2_654_435_761) >> 24) as u8;
+///         [value, value, value, 255]
+///     }).collect(),
+///     64,
+///     64,
+/// );
+/// let actual = ImgVec::new(
+///     expected.pixels().enumerate().map(|(i, [r, g, b, a])| {
+///         let noise = (i % 3) as u8;
+///         [r.saturating_add(noise), g.saturating_add(noise), b.saturating_add(noise), a]
+///     }).collect(),
+///     64,
+///     64,
+/// );
+///
+/// let ssim = rendiff::ssim(actual.as_ref(), expected.as_ref()).unwrap();
+/// assert!(ssim.score() > 0.99);
+/// // By contrast, the pixel-by-pixel comparison finds differences almost everywhere.
+/// let difference = rendiff::diff(actual.as_ref(), expected.as_ref());
+/// assert!(difference.histogram().differing_fraction() > 0.5);
+/// ```
+///
+/// [SSIM]: https://en.wikipedia.org/wiki/Structural_similarity_index_measure
+#[must_use]
+pub fn ssim(actual: ImgRef<'_, RgbaPixel>, expected: ImgRef<'_, RgbaPixel>) -> Option<Ssim> {
+    let [width, height] = dimensions(expected);
+    if dimensions(actual) != dimensions(expected) || width < WINDOW_SIZE || height < WINDOW_SIZE {
+        return None;
+    }
+    let area = Rect::new(
+        WINDOW_RADIUS,
+        WINDOW_RADIUS,
+        width - WINDOW_SIZE + 1,
+        height - WINDOW_SIZE + 1,
+    );
+
+    let luma = |image: ImgRef<'_, RgbaPixel>| -> Vec<f64> {
+        image
+            .pixels()
+            .map(|pixel| f64::from(rgba_to_luma(pixel)))
+            .collect()
+    };
+    let actual = luma(actual);
+    let expected = luma(expected);
+    let product =
+        |x: &[f64], y: &[f64]| -> Vec<f64> { x.iter().zip(y).map(|(x, y)| x * y).collect() };
+
+    let kernel = gaussian_kernel();
+    let filter = |values: &[f64]| window_filter(values, width, height, &kernel);
+    // Weighted means of a, e, a², e², and ae in each window, where a is a pixel of `actual`
+    // and e is the corresponding pixel of `expected`.
+    let means = [
+        filter(&actual),
+        filter(&expected),
+        filter(&product(&actual, &actual)),
+        filter(&product(&expected, &expected)),
+        filter(&product(&actual, &expected)),
+    ];
+
+    let map: Vec<f32> = (0..means[0].len())
+        .map(|i| {
+            let [mu_actual, mu_expected, power_actual, power_expected, cross] =
+                [0, 1, 2, 3, 4].map(|which| means[which][i]);
+            let variance_actual = power_actual - mu_actual * mu_actual;
+            let variance_expected = power_expected - mu_expected * mu_expected;
+            let covariance = cross - mu_actual * mu_expected;
+            let ssim = ((2.0 * mu_actual * mu_expected + C1) * (2.0 * covariance + C2))
+                / ((mu_actual * mu_actual + mu_expected * mu_expected + C1)
+                    * (variance_actual + variance_expected + C2));
+            #[allow(clippy::cast_possible_truncation)]
+            let ssim = ssim as f32;
+            ssim
+        })
+        .collect();
+
+    #[allow(clippy::cast_precision_loss)] // window counts are exact in f64 up to 2^53
+    let score = map.iter().copied().map(f64::from).sum::<f64>() / map.len() as f64;
+    let map = ImgVec::new(map, area.width, area.height);
+    let histogram = histogram_of(magnitudes(map.as_ref()).as_ref());
+    Some(Ssim {
+        area,
+        map,
+        score,
+        histogram,
+    })
+}
+
+impl Ssim {
+    /// Returns the mean SSIM of all windows, which is the conventional overall score.
+    ///
+    /// This is 1 if the images are identical, and smaller the more they differ;
+    /// it is usually between 0 and 1 but can be as low as −1.
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Returns the SSIM of the window centered on each pixel of [`Ssim::area()`].
+    ///
+    /// Pixel (x, y) of the map corresponds to pixel (x + area.x, y + area.y) of the
+    /// input images.
+    #[must_use]
+    pub fn map(&self) -> ImgRef<'_, f32> {
+        self.map.as_ref()
+    }
+
+    /// Returns the rectangle of pixels of the input images which are the centers of the
+    /// windows in [`Ssim::map()`].
+    #[must_use]
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Returns a histogram of the dissimilarity of each window, so that the SSIM can be
+    /// judged by a [`Threshold`](crate::Threshold) just like the results of
+    /// [`diff()`](crate::diff()).
+    ///
+    /// The dissimilarity magnitude of a window is `(1 − SSIM) / 2`, scaled to the range
+    /// 0–255 and rounded.
+    #[must_use]
+    pub fn histogram(&self) -> Histogram {
+        self.histogram
+    }
+
+    /// Creates an image of the dissimilarity of each window, in the same style as
+    /// [`Difference::diff_image()`](crate::Difference::diff_image).
+    ///
+    /// The image covers [`Ssim::area()`].
+    /// `expected` must be the same `expected` image that was given to [`ssim()`].
+    ///
+    /// # Panics
+    ///
+    /// May panic if `expected` is not of the same size as the original.
+    #[must_use]
+    pub fn render_diff_image(&self, expected: ImgRef<'_, RgbaPixel>) -> ImgVec<RgbaPixel> {
+        crate::visualize::visualize(
+            self.area.expand(1).sub_image(expected),
+            magnitudes(self.map.as_ref()).as_ref(),
+            &self.histogram,
+            Vec::new(),
+        )
+    }
+}
+
+/// Converts SSIM values to dissimilarity magnitudes as described in [`Ssim::histogram()`].
+fn magnitudes(map: ImgRef<'_, f32>) -> ImgVec<u8> {
+    ImgVec::new(
+        map.pixels()
+            .map(|ssim| {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let magnitude = ((1.0 - ssim) / 2.0 * 255.0).round().clamp(0.0, 255.0) as u8;
+                magnitude
+            })
+            .collect(),
+        map.width(),
+        map.height(),
+    )
+}
+
+/// Returns the normalized Gaussian weights of the SSIM window.
+fn gaussian_kernel() -> [f64; WINDOW_SIZE] {
+    let mut kernel = [0.0; WINDOW_SIZE];
+    for (index, weight) in kernel.iter_mut().enumerate() {
+        // Distance from the center of the window.
+        let offset = f64::from(u32::try_from(index.abs_diff(WINDOW_RADIUS)).unwrap());
+        *weight = (-(offset * offset) / (2.0 * WINDOW_SIGMA * WINDOW_SIGMA)).exp();
+    }
+    let sum: f64 = kernel.iter().sum();
+    kernel.map(|weight| weight / sum)
+}
+
+/// Computes the weighted mean of `values`, which is a `width` by `height` image in row-major
+/// order, in each window which lies entirely within it.
+fn window_filter(
+    values: &[f64],
+    width: usize,
+    height: usize,
+    kernel: &[f64; WINDOW_SIZE],
+) -> Vec<f64> {
+    let out_width = width - WINDOW_SIZE + 1;
+    let out_height = height - WINDOW_SIZE + 1;
+    // The filter is separable, so filter rows and then columns.
+    let mut horizontal = Vec::with_capacity(out_width * height);
+    for row in values.chunks_exact(width) {
+        for x in 0..out_width {
+            horizontal.push(weighted_sum(kernel, &row[x..]));
+        }
+    }
+    let mut output = Vec::with_capacity(out_width * out_height);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let column = horizontal[y * out_width + x..].iter().step_by(out_width);
+            output.push(weighted_sum(kernel, column));
+        }
+    }
+    output
+}
+
+/// Returns the sum of the products of `kernel` and the first elements of `samples`.
+fn weighted_sum<'a>(
+    kernel: &[f64; WINDOW_SIZE],
+    samples: impl IntoIterator<Item = &'a f64>,
+) -> f64 {
+    kernel.iter().zip(samples).map(|(k, v)| k * v).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    fn texture(x: usize, y: usize) -> RgbaPixel {
+        luma_to_rgba(u8::try_from((x * 37 + y * 91 + x * y * 13) % 200).unwrap())
+    }
+
+    #[test]
+    fn identical() {
+        let image = crate::image::from_fn(20, 15, texture);
+        let ssim = ssim(image.as_ref(), image.as_ref()).unwrap();
+        assert!((ssim.score() - 1.0).abs() < 1e-9, "{}", ssim.score());
+        assert_eq!(ssim.area(), Rect::new(5, 5, 10, 5));
+        assert_eq!(ssim.histogram().max_difference(), 0);
+        assert_eq!(ssim.render_diff_image(image.as_ref()).width(), 10);
+    }
+
+    #[test]
+    fn local_change() {
+        let expected = crate::image::from_fn(40, 40, texture);
+        let actual = crate::image::from_fn(40, 40, |x, y| {
+            if (25..30).contains(&x) && (25..30).contains(&y) {
+                luma_to_rgba(100)
+            } else {
+                texture(x, y)
+            }
+        });
+        let ssim = ssim(actual.as_ref(), expected.as_ref()).unwrap();
+        assert!(ssim.score() < 0.99, "{}", ssim.score());
+
+        // The least similar window is centered in the changed square.
+        let map = ssim.map();
+        let (worst_x, worst_y) = (0..map.height())
+            .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+            .min_by(|&a, &b| map[a].total_cmp(&map[b]))
+            .unwrap();
+        let area = ssim.area();
+        assert!((25..30).contains(&(worst_x + area.x)), "{worst_x}");
+        assert!((25..30).contains(&(worst_y + area.y)), "{worst_y}");
+        // Windows far from the change are unaffected.
+        assert!((map[(0usize, 0usize)] - 1.0).abs() < 1e-6);
+        assert!(ssim.histogram().max_difference() > 0);
+    }
+
+    #[test]
+    fn too_small_or_mismatched() {
+        let small = crate::image::from_fn(10, 20, texture);
+        assert_eq!(ssim(small.as_ref(), small.as_ref()), None);
+        let image1 = crate::image::from_fn(20, 20, texture);
+        let image2 = crate::image::from_fn(20, 21, texture);
+        assert_eq!(ssim(image1.as_ref(), image2.as_ref()), None);
+    }
+}
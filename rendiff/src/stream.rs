@@ -0,0 +1,274 @@
+use imgref::ImgRef;
+
+use crate::{Histogram, RgbaPixel};
+
+#[cfg(doc)]
+use crate::{diff, Difference};
+
+/// Compares images which are provided incrementally, a row or a band of rows at a time,
+/// so that the entire image never needs to be in memory.
+///
+/// The results are the same as those of [`diff()`], but only the [`Histogram`] is retained;
+/// the difference values themselves may be delivered to a callback as each row of them
+/// is completed, instead of being stored in a [`Difference`].
+/// Only the 3 most recent rows of each input image are kept, that being the size of the
+/// neighborhood used for comparison.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::{imgref::ImgRef, StreamingDiffer, Threshold};
+///
+/// let row_of_expected = [[0, 0, 0, 255]; 100];
+/// let row_of_actual = [[1, 1, 1, 255]; 100];
+///
+/// let mut differ = StreamingDiffer::new(100);
+/// for _ in 0..100 {
+///     // In a real application, these rows would come from a file or a renderer.
+///     differ.push_rows_with_sink(
+///         ImgRef::new(&row_of_actual, 100, 1),
+///         ImgRef::new(&row_of_expected, 100, 1),
+///         |y, difference_row| {
+///             // `difference_row` contains the differences for the interior pixels of row `y`.
+///             assert_eq!(difference_row.len(), 98);
+///         },
+///     );
+/// }
+/// let histogram = differ.finish();
+///
+/// assert_eq!(histogram.max_difference(), 1);
+/// assert!(Threshold::no_bigger_than(1).allows(histogram));
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamingDiffer {
+    width: usize,
+
+    /// The most recently received rows of each image, oldest first.
+    /// Only the first `rows_received.min(3)` of them are meaningful.
+    actual_rows: [Vec<RgbaPixel>; 3],
+    expected_rows: [Vec<RgbaPixel>; 3],
+    rows_received: usize,
+
+    /// Scratch buffers for the output of each half-diff and the combination of them.
+    row_buffers: [Vec<u8>; 3],
+
+    histogram: Histogram,
+}
+
+impl StreamingDiffer {
+    /// Creates a [`StreamingDiffer`] which will compare images that are `width` pixels wide.
+    #[must_use]
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            actual_rows: Default::default(),
+            expected_rows: Default::default(),
+            rows_received: 0,
+            row_buffers: Default::default(),
+            histogram: Histogram::ZERO,
+        }
+    }
+
+    /// Provides the next rows of both images.
+    ///
+    /// `actual` and `expected` may have any height, but must have the same height as each
+    /// other, and the width given to [`StreamingDiffer::new()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes of `actual` and `expected` are not as described above.
+    pub fn push_rows(&mut self, actual: ImgRef<'_, RgbaPixel>, expected: ImgRef<'_, RgbaPixel>) {
+        self.push_rows_with_sink(actual, expected, |_, _| {});
+    }
+
+    /// Provides the next rows of both images, and passes each completed row of difference
+    /// values to `sink`.
+    ///
+    /// The difference values are those which would be used to compute the histogram;
+    /// they are not scaled for visibility as [`Difference::diff_image()`] is.
+    /// `sink` is called with the index of the input row the differences belong to, and
+    /// a slice of the differences for that row, whose first element belongs to column 1 of
+    /// the input (because the edges of the image are not compared).
+    /// Because each row's differences depend on the following row, `sink` is called
+    /// for the row before the last one provided, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes of `actual` and `expected` are not as described in
+    /// [`StreamingDiffer::push_rows()`].
+    pub fn push_rows_with_sink(
+        &mut self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        mut sink: impl FnMut(usize, &[u8]),
+    ) {
+        assert_eq!(
+            (actual.width(), expected.width()),
+            (self.width, self.width),
+            "width of rows given to StreamingDiffer must equal the width it was created with"
+        );
+        assert_eq!(
+            actual.height(),
+            expected.height(),
+            "StreamingDiffer must be given equal numbers of rows of actual and expected"
+        );
+
+        for (actual_row, expected_row) in actual.rows().zip(expected.rows()) {
+            self.push_one_row(actual_row, expected_row, &mut sink);
+        }
+    }
+
+    fn push_one_row(
+        &mut self,
+        actual_row: &[RgbaPixel],
+        expected_row: &[RgbaPixel],
+        sink: &mut impl FnMut(usize, &[u8]),
+    ) {
+        // Recycle the oldest row's allocation for the newest row.
+        for (rows, new_row) in [
+            (&mut self.actual_rows, actual_row),
+            (&mut self.expected_rows, expected_row),
+        ] {
+            rows.rotate_left(1);
+            rows[2].clear();
+            rows[2].extend_from_slice(new_row);
+        }
+        self.rows_received += 1;
+
+        if self.rows_received < 3 || self.width < 3 {
+            // There is not yet a complete neighborhood for any pixel.
+            return;
+        }
+
+        let [hd1, hd2, combined] = &mut self.row_buffers;
+        hd1.clear();
+        hd2.clear();
+        combined.clear();
+        let interior = 1..self.width - 1;
+        crate::diff::half_diff_row(
+            &self.expected_rows[1][interior.clone()],
+            as_slices(&self.actual_rows),
+            hd1,
+        );
+        crate::diff::half_diff_row(
+            &self.actual_rows[1][interior],
+            as_slices(&self.expected_rows),
+            hd2,
+        );
+
+        // Combine the two half_diff results exactly as `Differ::compare()` does.
+        combined.extend(hd1.iter().zip(hd2.iter()).map(|(&d1, &d2)| d1.max(d2)));
+        for &diff_value in combined.iter() {
+            self.histogram.0[usize::from(diff_value)] += 1;
+        }
+
+        sink(self.rows_received - 2, combined);
+    }
+
+    /// Returns the number of rows of each image which have been provided so far.
+    #[must_use]
+    pub fn rows_received(&self) -> usize {
+        self.rows_received
+    }
+
+    /// Returns the histogram of differences found so far, which will only include rows whose
+    /// neighborhoods are complete (that is, not the most recently provided row).
+    #[must_use]
+    pub fn histogram(&self) -> Histogram {
+        self.histogram
+    }
+
+    /// Finishes the comparison and returns the histogram of differences in the entire image.
+    ///
+    /// This is equal to [`Difference::histogram()`] for the same images.
+    /// In particular, if the images are too small to have any pixels to compare (fewer than
+    /// 3 rows were provided, or the width is less than 3), then every pixel is counted as
+    /// having the maximum difference.
+    #[must_use]
+    pub fn finish(self) -> Histogram {
+        if self.rows_received < 3 || self.width < 3 {
+            let mut histogram = Histogram::ZERO;
+            histogram.0[usize::from(u8::MAX)] = self.rows_received * self.width;
+            histogram
+        } else {
+            self.histogram
+        }
+    }
+}
+
+fn as_slices<T>(rows: &[Vec<T>; 3]) -> [&[T]; 3] {
+    [&rows[0], &rows[1], &rows[2]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    fn test_images() -> [imgref::ImgVec<RgbaPixel>; 2] {
+        [
+            crate::image::from_fn(9, 11, |x, _| luma_to_rgba(u8::from(x == 4) * 100)),
+            crate::image::from_fn(9, 11, |x, y| {
+                luma_to_rgba(u8::try_from(y / 3).unwrap() + u8::from(x == 3 && y < 6) * 100)
+            }),
+        ]
+    }
+
+    #[test]
+    fn equivalent_to_diff_in_any_band_size() {
+        let [actual, expected] = test_images();
+        let expected_histogram = crate::diff(actual.as_ref(), expected.as_ref()).histogram();
+
+        for band_height in 1..=actual.height() {
+            let mut differ = StreamingDiffer::new(actual.width());
+            let mut rows_seen = Vec::new();
+            let mut y = 0;
+            while y < actual.height() {
+                let h = band_height.min(actual.height() - y);
+                differ.push_rows_with_sink(
+                    actual.sub_image(0, y, actual.width(), h),
+                    expected.sub_image(0, y, expected.width(), h),
+                    |row_y, row| {
+                        assert_eq!(row.len(), actual.width() - 2);
+                        rows_seen.push(row_y);
+                    },
+                );
+                y += h;
+            }
+            assert_eq!(differ.rows_received(), actual.height());
+            assert_eq!(
+                (differ.finish(), rows_seen),
+                (
+                    expected_histogram,
+                    (1..actual.height() - 1).collect::<Vec<_>>()
+                ),
+                "band height {band_height}"
+            );
+        }
+    }
+
+    #[test]
+    fn too_small_to_compare() {
+        let [actual, expected] = test_images();
+        for [width, height] in [[9, 2], [2, 2], [2, 11], [1, 1]] {
+            let actual = actual.sub_image(0, 0, width, height);
+            let expected = expected.sub_image(0, 0, width, height);
+            let mut differ = StreamingDiffer::new(width);
+            differ.push_rows(actual, expected);
+            let histogram = differ.finish();
+            assert_eq!(
+                histogram,
+                crate::diff(actual, expected).histogram(),
+                "{width}×{height}"
+            );
+            assert_eq!(histogram.max_difference(), 255);
+        }
+    }
+
+    #[test]
+    #[should_panic = "width of rows given to StreamingDiffer must equal the width it was created with"]
+    fn wrong_width() {
+        let [actual, expected] = test_images();
+        StreamingDiffer::new(actual.width() + 1).push_rows(actual.as_ref(), expected.as_ref());
+    }
+}
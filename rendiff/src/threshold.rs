@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use crate::Histogram;
+use crate::{Difference, Histogram};
 
 /// A bound upon pixel differences observed in a [`Histogram`](crate::Histogram),
 /// which you may use to define the pass/fail criterion for your image comparison test.
@@ -104,9 +104,93 @@ impl From<u8> for Threshold {
     }
 }
 
+/// A bound upon the sizes and number of connected regions of differing pixels in a
+/// [`Difference`], which you may use in addition to a [`Threshold`] to define the pass/fail
+/// criterion for your image comparison test.
+///
+/// While a [`Threshold`] only considers how many differences there are, a [`RegionThreshold`]
+/// considers whether they are close together, so that, for example, many scattered
+/// single-pixel differences may be accepted while a solid blob of the same number of
+/// differing pixels is rejected.
+/// See [`Difference::regions()`] for how the regions are determined.
+///
+/// # Example
+///
+/// ```
+/// use rendiff::{imgref::ImgVec, RegionThreshold};
+///
+/// let expected = ImgVec::new(vec![[0, 0, 0, 255u8]; 20 * 20], 20, 20);
+/// let mut scattered = expected.clone();
+/// let mut blob = expected.clone();
+/// for i in 0..4 {
+///     scattered[(i * 4 + 2, 2usize)] = [10, 10, 10, 255];
+///     blob[(i % 2 + 2, i / 2 + 2)] = [10, 10, 10, 255];
+/// }
+///
+/// let threshold = RegionThreshold::new(0).max_region_size(1);
+/// assert!(threshold.allows(&rendiff::diff(scattered.as_ref(), expected.as_ref())));
+/// assert!(!threshold.allows(&rendiff::diff(blob.as_ref(), expected.as_ref())));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RegionThreshold {
+    level: u8,
+    max_region_size: usize,
+    max_region_count: usize,
+}
+
+impl RegionThreshold {
+    /// Creates a [`RegionThreshold`] which considers regions of pixels whose differences
+    /// are greater than `level`, and does not limit them.
+    ///
+    /// Use [`RegionThreshold::max_region_size()`] and
+    /// [`RegionThreshold::max_region_count()`] to add limits.
+    #[must_use]
+    pub fn new(level: u8) -> Self {
+        Self {
+            level,
+            max_region_size: usize::MAX,
+            max_region_count: usize::MAX,
+        }
+    }
+
+    /// Limits the number of pixels in each region.
+    #[must_use]
+    pub fn max_region_size(mut self, pixels: usize) -> Self {
+        self.max_region_size = pixels;
+        self
+    }
+
+    /// Limits the number of regions.
+    #[must_use]
+    pub fn max_region_count(mut self, count: usize) -> Self {
+        self.max_region_count = count;
+        self
+    }
+
+    /// Returns whether the differences described by the given [`Difference`] are permitted
+    /// by this [`RegionThreshold`].
+    ///
+    /// If the images had different sizes, or were too small to compare, this returns
+    /// `false`, regardless of the limits.
+    #[must_use]
+    pub fn allows(&self, difference: &Difference) -> bool {
+        if !difference.sizes_matched() {
+            // There are no regions to examine, and the images cannot be acceptable.
+            return false;
+        }
+
+        let regions = difference.regions(self.level);
+        regions.len() <= self.max_region_count
+            && regions
+                .iter()
+                .all(|region| region.pixel_count <= self.max_region_size)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::image::luma_to_rgba;
 
     const H1: Histogram = {
         let mut h = [0; 256];
@@ -164,4 +248,79 @@ mod tests {
             Histogram(h)
         }));
     }
+
+    /// Returns the difference between a black image and one with the given pixels set to
+    /// the given brightness.
+    fn diff_with_pixels(pixels: &[(usize, usize, u8)]) -> Difference {
+        let expected = crate::image::from_fn(10, 10, |_, _| luma_to_rgba(0));
+        let actual = crate::image::from_fn(10, 10, |x, y| {
+            luma_to_rgba(
+                pixels
+                    .iter()
+                    .find(|&&(px, py, _)| (px, py) == (x, y))
+                    .map_or(0, |&(_, _, value)| value),
+            )
+        });
+        crate::diff(actual.as_ref(), expected.as_ref())
+    }
+
+    #[test]
+    fn region_unlimited() {
+        let difference = diff_with_pixels(&[(1, 1, 100), (2, 2, 100), (5, 5, 100)]);
+        assert!(RegionThreshold::new(0).allows(&difference));
+    }
+
+    #[test]
+    fn region_size() {
+        let difference = diff_with_pixels(&[(1, 1, 100), (2, 2, 100), (5, 5, 100)]);
+        assert_eq!(
+            (
+                RegionThreshold::new(0)
+                    .max_region_size(1)
+                    .allows(&difference),
+                RegionThreshold::new(0)
+                    .max_region_size(2)
+                    .allows(&difference),
+            ),
+            (false, true)
+        );
+    }
+
+    #[test]
+    fn region_count() {
+        let difference = diff_with_pixels(&[(1, 1, 100), (2, 2, 100), (5, 5, 100)]);
+        assert_eq!(
+            (
+                RegionThreshold::new(0)
+                    .max_region_count(1)
+                    .allows(&difference),
+                RegionThreshold::new(0)
+                    .max_region_count(2)
+                    .allows(&difference),
+            ),
+            (false, true)
+        );
+    }
+
+    #[test]
+    fn region_level_excludes_small_differences() {
+        let difference = diff_with_pixels(&[(1, 1, 5), (2, 2, 100), (5, 5, 100)]);
+        let threshold = RegionThreshold::new(5).max_region_size(1);
+        assert!(threshold.allows(&difference));
+        assert!(!RegionThreshold::new(4)
+            .max_region_size(1)
+            .allows(&difference));
+    }
+
+    #[test]
+    fn region_mismatched_sizes() {
+        let [actual, expected] = crate::image::mismatched_images();
+        let difference = crate::diff(actual.as_ref(), expected.as_ref());
+        assert!(!RegionThreshold::new(0).allows(&difference));
+        // Not even the most permissive threshold permits this.
+        assert!(!RegionThreshold::new(255).allows(&difference));
+        assert!(!RegionThreshold::new(255)
+            .max_region_count(0)
+            .allows(&difference));
+    }
 }
@@ -0,0 +1,180 @@
+use imgref::ImgRef;
+
+use crate::diff::pixel_diff;
+use crate::{Rect, RgbaPixel};
+
+/// Finds the translation of `actual` relative to `expected`, no larger than `max_offset` in
+/// either axis, which minimizes the mean difference between their overlapping pixels.
+///
+/// The result `[dx, dy]` means that `actual[(x + dx, y + dy)]` corresponds to
+/// `expected[(x, y)]`. Ties are broken in favor of smaller translations.
+/// Translations are further limited so that the overlap is at least [`min_overlap()`] in
+/// each axis, so that a small overlap which happens to match cannot win over the true
+/// translation.
+///
+/// The images must have the same size.
+pub(crate) fn find_translation(
+    actual: ImgRef<'_, RgbaPixel>,
+    expected: ImgRef<'_, RgbaPixel>,
+    max_offset: usize,
+) -> [isize; 2] {
+    let axis_limit = |size: usize| {
+        isize::try_from(max_offset.min(size.saturating_sub(min_overlap(size))))
+            .unwrap_or(isize::MAX)
+    };
+    let [x_limit, y_limit] = [expected.width(), expected.height()].map(axis_limit);
+
+    let mut candidates: Vec<[isize; 2]> = Vec::new();
+    for dy in -y_limit..=y_limit {
+        for dx in -x_limit..=x_limit {
+            candidates.push([dx, dy]);
+        }
+    }
+    candidates.sort_by_key(|&[dx, dy]| dx.unsigned_abs().max(dy.unsigned_abs()));
+
+    // Best (sum of differences, number of pixels, offset) found so far.
+    let mut best: Option<(u64, u64, [isize; 2])> = None;
+    for offset in candidates {
+        let [actual_part, expected_part] = overlapping_parts(actual, expected, offset);
+        let sum = total_difference(actual_part, expected_part);
+        let count = actual_part.pixels().len() as u64;
+        // Compare the means sum/count without division.
+        let is_better = best.map_or(true, |(best_sum, best_count, _)| {
+            u128::from(sum) * u128::from(best_count) < u128::from(best_sum) * u128::from(count)
+        });
+        if is_better {
+            best = Some((sum, count, offset));
+        }
+    }
+    best.map_or([0, 0], |(_, _, offset)| offset)
+}
+
+/// Returns the smallest overlap, in one axis of size `size`, which [`find_translation()`]
+/// will consider: half of the size, but no less than 3 pixels, which is the smallest image
+/// that has any pixels to compare.
+fn min_overlap(size: usize) -> usize {
+    size.div_ceil(2).max(3)
+}
+
+/// Returns the sum of the differences between corresponding pixels of two images of the
+/// same size.
+pub(crate) fn total_difference(a: ImgRef<'_, RgbaPixel>, b: ImgRef<'_, RgbaPixel>) -> u64 {
+    a.pixels()
+        .zip(b.pixels())
+        .map(|(a_pixel, b_pixel)| u64::from(pixel_diff(a_pixel, b_pixel)))
+        .sum()
+}
+
+/// Returns the sum of the differences between corresponding pixels of two images of the
+/// same size, or [`None`] if it is greater than `limit`.
+///
+/// Stops early once `limit` is exceeded.
+pub(crate) fn total_difference_up_to(
+    a: ImgRef<'_, RgbaPixel>,
+    b: ImgRef<'_, RgbaPixel>,
+    limit: u64,
+) -> Option<u64> {
+    let mut sum = 0u64;
+    for (a_row, b_row) in a.rows().zip(b.rows()) {
+        for (&a_pixel, &b_pixel) in a_row.iter().zip(b_row) {
+            sum += u64::from(pixel_diff(a_pixel, b_pixel));
+        }
+        if sum > limit {
+            return None;
+        }
+    }
+    Some(sum)
+}
+
+/// Returns the parts of `actual` and `expected` which overlap when `actual` is translated
+/// by `-offset`, such that the returned images correspond pixel-for-pixel.
+///
+/// The images must have the same size, and `offset` must be smaller than that size.
+pub(crate) fn overlapping_parts<'a>(
+    actual: ImgRef<'a, RgbaPixel>,
+    expected: ImgRef<'a, RgbaPixel>,
+    offset: [isize; 2],
+) -> [ImgRef<'a, RgbaPixel>; 2] {
+    let [x, y] = overlap_origin(offset);
+    let area = Rect::new(
+        x,
+        y,
+        expected.width() - offset[0].unsigned_abs(),
+        expected.height() - offset[1].unsigned_abs(),
+    );
+    let actual_area = Rect {
+        x: area.x.wrapping_add_signed(offset[0]),
+        y: area.y.wrapping_add_signed(offset[1]),
+        ..area
+    };
+    [actual_area.sub_image(actual), area.sub_image(expected)]
+}
+
+/// Returns the position in the `expected` image of the parts returned by
+/// [`overlapping_parts()`].
+pub(crate) fn overlap_origin([dx, dy]: [isize; 2]) -> [usize; 2] {
+    [
+        usize::try_from(-dx).unwrap_or(0),
+        usize::try_from(-dy).unwrap_or(0),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn finds_translation() {
+        let pattern =
+            |x: usize, y: usize| luma_to_rgba(u8::try_from((x * 7 + y * 13) % 64 * 4).unwrap());
+        let expected = crate::image::from_fn(20, 15, pattern);
+        // actual[(x + 3, y - 1)] == expected[(x, y)], since the pattern repeats every 64.
+        let actual = crate::image::from_fn(20, 15, |x, y| pattern(x + 61, y + 1));
+        assert_eq!(
+            find_translation(actual.as_ref(), expected.as_ref(), 4),
+            [3, -1]
+        );
+        assert_eq!(
+            find_translation(expected.as_ref(), expected.as_ref(), 4),
+            [0, 0]
+        );
+    }
+
+    /// A small overlap which happens to match exactly must not win over the true translation,
+    /// which matches all but one pixel.
+    #[test]
+    fn minimum_overlap() {
+        let pattern =
+            |x: usize, y: usize| luma_to_rgba(u8::try_from((x * 7 + y * 13) % 64 * 4).unwrap());
+        let expected = crate::image::from_fn(10, 10, pattern);
+        let actual = crate::image::from_fn(10, 10, |x, y| match (x, y) {
+            // Matches expected[(9, 9)], as if translated by [-9, -9].
+            (0, 0) => pattern(9, 9),
+            (5, 5) => [255, 0, 0, 255],
+            // Otherwise, translated by [2, 0].
+            _ => pattern(x + 62, y),
+        });
+        assert_eq!(
+            find_translation(actual.as_ref(), expected.as_ref(), 20),
+            [2, 0]
+        );
+        assert_eq!(min_overlap(10), 5);
+        assert_eq!(min_overlap(4), 3);
+        assert_eq!(min_overlap(2), 3);
+    }
+
+    #[test]
+    fn overlap() {
+        let expected =
+            crate::image::from_fn(5, 4, |x, y| luma_to_rgba(u8::try_from(x + y * 10).unwrap()));
+        let actual = crate::image::from_fn(5, 4, |x, y| {
+            luma_to_rgba(u8::try_from(x + y * 10 + 100).unwrap())
+        });
+        let [a, e] = overlapping_parts(actual.as_ref(), expected.as_ref(), [2, -1]);
+        assert_eq!((e.width(), e.height()), (3, 3));
+        assert_eq!(overlap_origin([2, -1]), [0, 1]);
+        assert_eq!(e[(0usize, 0usize)], luma_to_rgba(10));
+        assert_eq!(a[(0usize, 0usize)], luma_to_rgba(102));
+    }
+}
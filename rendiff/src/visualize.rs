@@ -1,40 +1,103 @@
-use imgref::{ImgRef, ImgVec};
+use imgref::{ImgRef, ImgRefMut, ImgVec};
 
-use crate::{Histogram, RgbaPixel};
+use crate::{Histogram, Rect, RgbaPixel};
 
 /// Take the raw absolute-difference values and visualize them
 /// (by making small values more visible).
+///
+/// `buffer` is used as the allocation for the output image; its contents are discarded.
 pub(crate) fn visualize(
     reference: ImgRef<'_, RgbaPixel>,
     raw_diff_image: ImgRef<'_, u8>,
     histogram: &Histogram,
+    mut buffer: Vec<RgbaPixel>,
 ) -> ImgVec<RgbaPixel> {
+    let (width, height) = (raw_diff_image.width(), raw_diff_image.height());
+    buffer.clear();
+    buffer.resize(width * height, [0; 4]);
+    let mut output = ImgVec::new(buffer, width, height);
+    visualize_region(
+        reference,
+        raw_diff_image,
+        histogram,
+        Rect::new(0, 0, width, height),
+        output.as_mut(),
+    );
+    output
+}
+
+/// Like [`visualize()`], but the output is the same size as `reference`, which is the entire
+/// input image of which only `compared_area` was compared, and the pixels outside of
+/// `compared_area` are drawn in a distinct style to show they were not compared.
+pub(crate) fn visualize_full_size(
+    reference: ImgRef<'_, RgbaPixel>,
+    compared_area: Rect,
+    raw_diff_image: ImgRef<'_, u8>,
+    histogram: &Histogram,
+    mut buffer: Vec<RgbaPixel>,
+) -> ImgVec<RgbaPixel> {
+    buffer.clear();
+    buffer.extend(reference.pixels().map(|pixel| {
+        // Distinguishable from compared pixels because green and blue are unequal.
+        [crate::image::rgba_to_luma(pixel) / 3, 0, 128, 255]
+    }));
+    let mut output = ImgVec::new(buffer, reference.width(), reference.height());
+    visualize_region(
+        compared_area.expand(1).sub_image(reference),
+        raw_diff_image,
+        histogram,
+        Rect::new(0, 0, raw_diff_image.width(), raw_diff_image.height()),
+        output.sub_image_mut(
+            compared_area.x,
+            compared_area.y,
+            compared_area.width,
+            compared_area.height,
+        ),
+    );
+    output
+}
+
+/// Like [`visualize()`], but only updates the pixels of `output` within `region`
+/// (in the coordinates of `raw_diff_image`).
+pub(crate) fn visualize_region(
+    reference: ImgRef<'_, RgbaPixel>,
+    raw_diff_image: ImgRef<'_, u8>,
+    histogram: &Histogram,
+    region: Rect,
+    mut output: ImgRefMut<'_, RgbaPixel>,
+) {
     // Validate the assumption our `(x + 1, y + 1)` coordinate lookups are making.
     // This will fail if we change how the diff algorithm works and don't update this.
     debug_assert_eq!(
         (reference.width(), reference.height()),
         (raw_diff_image.width() + 2, raw_diff_image.height() + 2)
     );
+    debug_assert_eq!(
+        (output.width(), output.height()),
+        (raw_diff_image.width(), raw_diff_image.height())
+    );
 
     let max_difference = f64::from(histogram.max_difference());
 
-    crate::image::from_fn(raw_diff_image.width(), raw_diff_image.height(), |x, y| {
-        // TODO: this should be re-encoded luminance, not luma
-        let reference_value = crate::image::rgba_to_luma(reference[(x + 1, y + 1)]);
-
-        // Scale up the diff values to maximize contrast
-        let raw_diff_value = raw_diff_image[(x, y)];
-        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-        let amplified_difference = (f64::from(raw_diff_value) / max_difference * 255.0) as u8;
-
-        [
-            // Make the reference image low-contrast (in the red channel and scaled down),
-            // so that it doesn't distract from the diff pixels but just gives visual context
-            // for the spatial position of the differences.
-            reference_value / 3,
-            amplified_difference,
-            amplified_difference,
-            255,
-        ]
-    })
+    for y in region.y..region.y_end() {
+        for x in region.x..region.x_end() {
+            // TODO: this should be re-encoded luminance, not luma
+            let reference_value = crate::image::rgba_to_luma(reference[(x + 1, y + 1)]);
+
+            // Scale up the diff values to maximize contrast
+            let raw_diff_value = raw_diff_image[(x, y)];
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let amplified_difference = (f64::from(raw_diff_value) / max_difference * 255.0) as u8;
+
+            output[(x, y)] = [
+                // Make the reference image low-contrast (in the red channel and scaled down),
+                // so that it doesn't distract from the diff pixels but just gives visual context
+                // for the spatial position of the differences.
+                reference_value / 3,
+                amplified_difference,
+                amplified_difference,
+                255,
+            ];
+        }
+    }
 }
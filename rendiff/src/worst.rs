@@ -0,0 +1,219 @@
+use core::fmt;
+
+use imgref::ImgRef;
+
+use crate::diff::{dimensions, pixel_diff};
+use crate::{Difference, Direction, Rect, RgbaPixel};
+
+/// Details of the difference at a single pixel, including the pixel values involved,
+/// produced by [`Difference::worst_pixels()`].
+///
+/// Its [`Display`](fmt::Display) implementation produces a line such as
+/// `(412, 87): expected #FF0000FF, actual #FE0000FF, best neighbor #FF0000FF, Δ3`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct PixelReport {
+    /// X coordinate of the pixel in the input images.
+    pub x: usize,
+    /// Y coordinate of the pixel in the input images.
+    pub y: usize,
+    /// Magnitude of the difference, on the same scale as the [`Histogram`](crate::Histogram).
+    pub magnitude: u8,
+    /// Value of the pixel in the `expected` image.
+    pub expected: RgbaPixel,
+    /// Value of the pixel in the `actual` image.
+    pub actual: RgbaPixel,
+    /// The direction of comparison which found the larger difference at this pixel.
+    pub direction: Direction,
+    /// The pixel from the neighborhood of this pixel which was the closest match.
+    ///
+    /// If [`PixelReport::direction`] is [`Direction::Extra`], this is the pixel of the
+    /// `expected` image which most closely matches [`PixelReport::actual`]; if it is
+    /// [`Direction::Missing`], this is the pixel of the `actual` image which most closely
+    /// matches [`PixelReport::expected`].
+    pub best_neighbor: RgbaPixel,
+}
+
+impl fmt::Display for PixelReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}): expected {}, actual {}, best neighbor {}, Δ{}",
+            self.x,
+            self.y,
+            Hex(self.expected),
+            Hex(self.actual),
+            Hex(self.best_neighbor),
+            self.magnitude
+        )
+    }
+}
+
+/// Formats a pixel as `#RRGGBBAA`.
+pub(crate) struct Hex(pub(crate) RgbaPixel);
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [red, green, blue, alpha] = self.0;
+        write!(f, "#{red:02X}{green:02X}{blue:02X}{alpha:02X}")
+    }
+}
+
+impl Difference {
+    /// Returns up to `count` of the pixels with the largest difference magnitudes,
+    /// from largest to smallest, with the values of the images at those pixels.
+    ///
+    /// Pixels with equal magnitudes are returned in row-major order.
+    /// Only pixels with nonzero differences are included.
+    /// Coordinates are those of the input images.
+    /// Pixel values are those of the input images, except that if
+    /// [color correction](crate::Differ::correct_color) was used, the `actual` values are
+    /// corrected, and if [edge comparison](crate::Differ::compare_edges) was used, all
+    /// values are of the edge maps; that is, they are the values which were compared.
+    ///
+    /// `actual` and `expected` must be the same images that were given to [`diff()`].
+    /// If the images had different sizes, there are no pixels to report.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `actual` or `expected` is not of the same size as the originals.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rendiff::imgref::ImgVec;
+    ///
+    /// let expected = ImgVec::new(vec![[255, 0, 0, 255u8]; 10 * 10], 10, 10);
+    /// let mut actual = expected.clone();
+    /// actual[(4usize, 7usize)] = [254, 0, 0, 255];
+    /// actual[(2usize, 2usize)] = [0, 0, 0, 255];
+    ///
+    /// let difference = rendiff::diff(actual.as_ref(), expected.as_ref());
+    /// let worst = difference.worst_pixels(actual.as_ref(), expected.as_ref(), 1);
+    /// assert_eq!(
+    ///     worst[0].to_string(),
+    ///     "(2, 2): expected #FF0000FF, actual #000000FF, best neighbor #FF0000FF, Δ54"
+    /// );
+    /// ```
+    ///
+    /// [`diff()`]: crate::diff()
+    #[must_use]
+    pub fn worst_pixels(
+        &self,
+        actual: ImgRef<'_, RgbaPixel>,
+        expected: ImgRef<'_, RgbaPixel>,
+        count: usize,
+    ) -> Vec<PixelReport> {
+        if !self.sizes_matched() {
+            return Vec::new();
+        }
+        assert_eq!(
+            dimensions(actual),
+            dimensions(expected),
+            "images given to worst_pixels() must have the same size"
+        );
+
+        let mut pixels: Vec<_> = self.differing_pixels().collect();
+        // Stable sort preserves row-major order among equal magnitudes.
+        pixels.sort_by_key(|d| core::cmp::Reverse(d.magnitude));
+        pixels.truncate(count);
+
+        let [origin_x, origin_y] = self.compared_origin();
+        self.with_compared_images(actual, expected, |[compared_actual, compared_expected]| {
+            pixels
+                .into_iter()
+                .map(|d| {
+                    let (x, y) = (d.x - origin_x, d.y - origin_y);
+                    let direction = if d.directional_magnitude(Direction::Extra)
+                        >= d.directional_magnitude(Direction::Missing)
+                    {
+                        Direction::Extra
+                    } else {
+                        Direction::Missing
+                    };
+                    let (have, want) = match direction {
+                        Direction::Missing => (compared_expected, compared_actual),
+                        Direction::Extra => (compared_actual, compared_expected),
+                    };
+                    PixelReport {
+                        x: d.x,
+                        y: d.y,
+                        magnitude: d.magnitude,
+                        expected: compared_expected[(x, y)],
+                        actual: compared_actual[(x, y)],
+                        direction,
+                        best_neighbor: best_neighbor(have[(x, y)], want, x, y),
+                    }
+                })
+                .collect()
+        })
+    }
+}
+
+/// Returns the pixel of `image` in the 3×3 neighborhood of `(x, y)` which is most similar to
+/// `pixel`, preferring the first in row-major order.
+fn best_neighbor(pixel: RgbaPixel, image: ImgRef<'_, RgbaPixel>, x: usize, y: usize) -> RgbaPixel {
+    let neighborhood = Rect::new(x - 1, y - 1, 3, 3).sub_image(image);
+    let mut best = neighborhood[(0usize, 0usize)];
+    for candidate in neighborhood.pixels() {
+        if pixel_diff(pixel, candidate) < pixel_diff(pixel, best) {
+            best = candidate;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::luma_to_rgba;
+
+    #[test]
+    fn order_and_count() {
+        let expected = crate::image::from_fn(8, 8, |_, _| luma_to_rgba(100));
+        let actual = crate::image::from_fn(8, 8, |x, y| {
+            luma_to_rgba(match (x, y) {
+                (5, 2) => 110,
+                (2, 3) | (6, 6) => 130,
+                (1, 1) => 50,
+                _ => 100,
+            })
+        });
+        let difference = crate::diff(actual.as_ref(), expected.as_ref());
+        let worst = difference.worst_pixels(actual.as_ref(), expected.as_ref(), 3);
+        assert_eq!(
+            worst
+                .iter()
+                .map(|r| (r.x, r.y, r.magnitude, r.direction))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 1, 50, Direction::Extra),
+                (2, 3, 30, Direction::Extra),
+                (6, 6, 30, Direction::Extra),
+            ]
+        );
+        assert_eq!(worst[0].actual, luma_to_rgba(50));
+        assert_eq!(worst[0].expected, luma_to_rgba(100));
+        assert_eq!(worst[0].best_neighbor, luma_to_rgba(100));
+
+        assert_eq!(
+            difference
+                .worst_pixels(actual.as_ref(), expected.as_ref(), 100)
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn missing_direction() {
+        let expected =
+            crate::image::from_fn(5, 5, |x, y| luma_to_rgba(u8::from((x, y) == (2, 2)) * 200));
+        let actual =
+            crate::image::from_fn(5, 5, |x, y| luma_to_rgba(u8::from((x, y) == (2, 2)) * 20));
+        let difference = crate::diff(actual.as_ref(), expected.as_ref());
+        let worst = difference.worst_pixels(actual.as_ref(), expected.as_ref(), 1);
+        assert_eq!(worst[0].direction, Direction::Missing);
+        assert_eq!(worst[0].best_neighbor, luma_to_rgba(20));
+        assert_eq!(worst[0].magnitude, 180);
+    }
+}
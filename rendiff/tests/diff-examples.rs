@@ -31,6 +31,37 @@ fn diff_example_robot() {
     );
 }
 
+/// The regions of the robot example should be the gap at the bottom and the gaps
+/// between each pair of teeth.
+#[test]
+fn regions_example_robot() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("example-comparisons");
+    let input_actual = load_and_convert(&root.join("robot-actual.png")).unwrap();
+    let input_expected = load_and_convert(&root.join("robot-exp.png")).unwrap();
+
+    let difference = rendiff::diff(input_actual.as_ref(), input_expected.as_ref());
+
+    assert_eq!(
+        difference
+            .regions(0)
+            .into_iter()
+            .map(|region| (
+                region.bounding_box,
+                region.pixel_count,
+                region.max_magnitude
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (rendiff::Rect::new(3, 126, 122, 1), 122, 169),
+            (rendiff::Rect::new(31, 95, 2, 2), 4, 85),
+            (rendiff::Rect::new(47, 95, 2, 2), 4, 85),
+            (rendiff::Rect::new(63, 95, 2, 2), 4, 85),
+            (rendiff::Rect::new(79, 95, 2, 2), 4, 85),
+            (rendiff::Rect::new(95, 95, 2, 2), 4, 85),
+        ]
+    );
+}
+
 fn load_and_convert(path: &Path) -> Result<imgref::ImgVec<[u8; 4]>, image::ImageError> {
     Ok(interop::from_rgba(image::open(path)?.to_rgba8()))
 }